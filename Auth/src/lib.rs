@@ -0,0 +1,197 @@
+use axum::{Json, TypedHeader};
+use axum::extract::State;
+use axum::routing::post;
+use axum::{
+    routing::get,
+    Router,
+};
+use shared::extract::OptionalJson;
+use shared::identity::{ProviderKind, VerifiedIdentity};
+use shared::migrations::CURRENT_SCHEMA_VERSION;
+use shared::session::{add_session, hash_token, SessionTokens};
+use shared::matches::DEFAULT_PAGE_SIZE;
+use shared::{AppError, AppState, get_user_document, update_user, Identity, MatchViewPage, MyProfile, Session, UserDocument};
+use uuid::Uuid;
+use std::sync::Arc;
+use axum::headers::Authorization;
+use axum::headers::authorization::Bearer;
+use serde::{Deserialize, Serialize};
+use shared::AppError::AuthError;
+
+#[derive(Deserialize)]
+struct AuthBody {
+    /// Provider which issued the ID token, Google by default.
+    #[serde(default)]
+    provider: ProviderKind,
+    id_token: String,
+    /// Name of the user, for providers which don't include it in the ID token.
+    name: Option<String>,
+    /// Identifies the device logging in, so that it keeps a single session.
+    device_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RefreshBody {
+    refresh_token: String,
+}
+
+#[derive(Deserialize, Default)]
+struct LogoutBody {
+    /// Revokes the sessions of every device instead of only the current one.
+    #[serde(default)]
+    all_sessions: bool,
+}
+
+#[derive(Serialize)]
+struct AuthResponse {
+    #[serde(flatten)]
+    profile: MyProfile,
+    #[serde(flatten)]
+    tokens: SessionTokens,
+}
+
+/// Profile of the user along with the first page of their matches.
+async fn load_profile(state: &AppState, user_document: &UserDocument) -> Result<MyProfile, AppError> {
+    let page = state
+        .match_store
+        .list_for_user(&user_document.id, None, DEFAULT_PAGE_SIZE)
+        .await?;
+    let matches = MatchViewPage::load(&*state.user_store, user_document, page).await?;
+    Ok(MyProfile::new(user_document, matches))
+}
+
+/// Finds the user owning a verified identity, along with whether it's a new user who isn't saved yet.
+/// Identities that aren't linked yet belong to the user with the same email, as long as the provider
+/// verified it, or to a new user.
+async fn find_or_register_user(state: &AppState, identity: &Identity, verified: VerifiedIdentity, fallback_name: Option<String>) -> Result<(UserDocument, bool), AppError> {
+    match state.user_store.get_by_identity(identity).await {
+        Err(AppError::NotFoundError) => {}
+        result => return result.map(|user_document| (user_document, false)),
+    }
+
+    let Some(email) = verified.email.filter(|_| verified.email_verified) else {
+        println!("No verified email for the {} identity, refusing login", identity.provider);
+        return Err(AuthError);
+    };
+    match state.user_store.get_by_email(&email).await {
+        Err(AppError::NotFoundError) => Ok((UserDocument {
+            id: String::from(Uuid::new_v4()),
+            schema_version: CURRENT_SCHEMA_VERSION,
+            // Apple only shares the name with the app once, which then sends it along the token
+            name: verified.name.or(fallback_name).unwrap_or_else(|| email.clone()),
+            email,
+            identities: Default::default(),
+            sessions: Default::default(),
+            description: None,
+            description_embeddings: None,
+            location: None,
+            blocked_user_ids: Default::default(),
+            calendar_feed_token_hash: None,
+        }, true)),
+        result => result.map(|user_document| (user_document, false)),
+    }
+}
+
+/// Handle login via OpenID Connect (Google, Apple or a configured generic provider).
+/// Each provider account is linked to a user, and accounts with the same verified email share the same user.
+/// Registers the user if necessary, and starts a new session for the device, with an access token
+/// to use for stateless authentication and a refresh token to renew it once it expires.
+/// Sessions are stored in the user's record, which any authenticated endpoint can check when a request arrives.
+/// Only hashes of the tokens are stored, so this is the only time the raw tokens are available.
+async fn handle_auth(State(state): State<Arc<AppState>>, Json(payload): Json<AuthBody>) -> Result<Json<AuthResponse>, AppError> {
+    println!("Auth started with {:?}", payload.provider);
+    let Some(provider) = state.identity_providers.get(payload.provider) else {
+        println!("{:?} login is not configured, refusing login", payload.provider);
+        return Err(AuthError);
+    };
+
+    let verified = provider.verify(&payload.id_token).await?;
+    let identity = Identity {
+        provider: provider.name().to_owned(),
+        subject: verified.subject.clone(),
+    };
+    let (mut user_document, registered) = find_or_register_user(&state, &identity, verified, payload.name).await?;
+
+    let (session, tokens) = Session::new(payload.device_id, &state.config);
+    let log_in = |user_document: &mut UserDocument| {
+        if !user_document.identities.contains(&identity) {
+            println!("Linking {} identity to user {}", identity.provider, user_document.id);
+            user_document.identities.push(identity.clone());
+        }
+        add_session(&mut user_document.sessions, session.clone());
+    };
+    let user_document = if registered {
+        log_in(&mut user_document);
+        state.user_store.upsert(&user_document).await?;
+        user_document
+    } else {
+        update_user(&*state.user_store, &user_document.id, |user_document| {
+            log_in(user_document);
+            Ok(true)
+        })
+        .await?
+    };
+    println!("User {} saved.", user_document.id);
+
+    Ok(Json(AuthResponse {
+        profile: load_profile(&state, &user_document).await?,
+        tokens,
+    }))
+}
+
+async fn refresh_profile(auth_header: TypedHeader<Authorization<Bearer>>, State(state): State<Arc<AppState>>) -> Result<Json<MyProfile>, AppError> {
+    println!("Refreshing profile");
+    let user_document = get_user_document(auth_header.token(), &state).await?;
+
+    Ok(Json(load_profile(&state, &user_document).await?))
+}
+
+/// Exchanges a refresh token for a new pair of tokens. Both previous tokens stop working.
+async fn refresh_session(State(state): State<Arc<AppState>>, Json(payload): Json<RefreshBody>) -> Result<Json<SessionTokens>, AppError> {
+    println!("Refreshing session");
+    let refresh_token_hash = hash_token(&state.config.token_hash_secret, &payload.refresh_token);
+    let user_document = state.user_store.get_by_refresh_token_hash(&refresh_token_hash).await?;
+    // A concurrent refresh with the same token rotates it first, and this one then fails
+    let mut tokens = None;
+    update_user(&*state.user_store, &user_document.id, |user_document| {
+        let session = user_document
+            .sessions
+            .iter_mut()
+            .find(|session| session.refresh_token_hash == refresh_token_hash)
+            .ok_or(AuthError)?;
+        session.check_refresh()?;
+        tokens = Some(session.rotate(&state.config));
+        Ok(true)
+    })
+    .await?;
+
+    Ok(Json(tokens.ok_or(AuthError)?))
+}
+
+/// Revokes the session the request is authenticated with, or every session of the user.
+async fn logout(auth_header: TypedHeader<Authorization<Bearer>>, State(state): State<Arc<AppState>>, OptionalJson(payload): OptionalJson<LogoutBody>) -> Result<(), AppError> {
+    let user_document = get_user_document(auth_header.token(), &state).await?;
+    let payload = payload.unwrap_or_default();
+    let access_token_hash = hash_token(&state.config.token_hash_secret, auth_header.token());
+
+    update_user(&*state.user_store, &user_document.id, |user_document| {
+        user_document
+            .sessions
+            .iter_mut()
+            .filter(|session| payload.all_sessions || session.access_token_hash == access_token_hash)
+            .for_each(Session::revoke);
+        Ok(true)
+    })
+    .await?;
+    println!("User {} logged out", user_document.id);
+    Ok(())
+}
+
+/// Routes served by this function, relative to the `/api` prefix.
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/auth", post(handle_auth))
+        .route("/auth", get(refresh_profile))
+        .route("/auth/refresh", post(refresh_session))
+        .route("/auth/logout", post(logout))
+}
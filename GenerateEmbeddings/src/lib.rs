@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    routing::post,
+    Json, Router, TypedHeader, headers::{authorization::Bearer, Authorization},
+};
+use serde::{Deserialize};
+use shared::{get_user_document, update_user, AppError, AppState, IndexAction, UserSearchData};
+
+#[derive(Deserialize)]
+struct TextEmbeddingsBody {
+    description: String,
+}
+
+/// Handler for text based data (e.g. a brief description written by the user of his interests)
+async fn generate_embeddings(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<TextEmbeddingsBody>,
+) -> Result<Json<()>, AppError> {
+    println!("Generate Embeddings start");
+    let user_document = get_user_document(auth_header.token(), &state).await?;
+
+    //convert data to embeddings through the configured provider (e.g. the OpenAI Ada model)
+    let embeddings = state.embedding_provider.embed(&payload.description).await?;
+    state.embedding_provider.validate(&embeddings)?;
+    println!("Embeddings generated with {}", state.embedding_provider.model());
+
+    // Save the data in the DB, both original (for user facing purposes) and vector data (in the indexed column)
+
+    let user_document = update_user(&*state.user_store, &user_document.id, |user_document| {
+        user_document.description = Some(payload.description.clone());
+        user_document.description_embeddings = Some(embeddings.clone());
+        Ok(true)
+    })
+    .await?;
+
+    let res = state.vector_index.index_documents(&[
+        IndexAction {
+            action_type: shared::IndexActionType::MergeOrUpload,
+            user_document: UserSearchData::from(user_document),
+        }
+    ]).await;
+    println!("Res: {:?}", res);
+    Ok(Json(()))
+}
+
+/// Routes served by this function, relative to the `/api` prefix.
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/generate_embeddings", post(generate_embeddings))
+}
@@ -0,0 +1,291 @@
+use std::sync::Arc;
+
+use axum::{extract::{Query, State}, routing::{get, post}, Json, Router, TypedHeader, headers::{Authorization, authorization::Bearer}};
+use serde::Deserialize;
+use shared::matches::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use shared::{get_user_document, retry_on_conflict, AppError, AppState, MatchAction, MatchRecord, MatchStatus, MatchStore, MatchView, MatchViewPage, RecommendationTicket};
+
+mod moderation;
+
+#[derive(Deserialize, Clone, Copy)]
+enum MatchOp {
+    Add,
+    Accept,
+    Reject,
+    Cancel,
+}
+
+impl MatchOp {
+    /// The change of an existing match, `None` when starting a new one.
+    fn action(self) -> Option<MatchAction> {
+        match self {
+            MatchOp::Add => None,
+            MatchOp::Accept => Some(MatchAction::Accept),
+            MatchOp::Reject => Some(MatchAction::Reject),
+            MatchOp::Cancel => Some(MatchAction::Cancel),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct AddMatchBody {
+    operation: MatchOp,
+    target_user_id: String,
+    /// Recommendation ticket returned by the query function, required to add a match.
+    ticket: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ListMatchesQuery {
+    cursor: Option<String>,
+    limit: Option<usize>,
+}
+
+/// Applies an operation to the match between two users, as requested by `user_id`.
+/// The match is a single record for both users, written only if it didn't change since it was read,
+/// so concurrent operations on the same match are retried on its latest state.
+async fn update_match(
+    match_store: &dyn MatchStore,
+    user_id: &str,
+    target_id: &str,
+    operation: MatchOp,
+) -> Result<MatchRecord, AppError> {
+    retry_on_conflict(&format!("Match {} - {}", user_id, target_id), || async move {
+        let existing = match_store.get(user_id, target_id).await?;
+        match (operation.action(), existing) {
+            (None, None) => {
+                let record = MatchRecord::new(user_id, target_id, MatchStatus::Pending);
+                match_store.create(&record).await.map(|_| record)
+            }
+            // Users can match again after a match was cancelled, but not after it was denied
+            (None, Some(existing)) if existing.document.status != MatchStatus::Cancelled => {
+                Err(AppError::GenericError)
+            }
+            (Some(_), None) => Err(AppError::NotFoundError),
+            (action, Some(mut existing)) => {
+                let status = match action {
+                    Some(action) => existing.document.status_for(user_id).transition(action)?,
+                    None => MatchStatus::Pending,
+                };
+                existing.document.set_status_for(user_id, status);
+                match_store
+                    .replace_if_match(&existing.document, &existing.etag)
+                    .await
+                    .map(|_| existing.document)
+            }
+        }
+    })
+    .await
+}
+
+async fn add_match(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<AddMatchBody>,
+) -> Result<Json<MatchView>, AppError> {
+    println!("Start");
+    let user_document =
+        get_user_document(auth_header.token(), &state).await?;
+    if user_document.id == payload.target_user_id {
+        return Err(AppError::GenericError);
+    }
+    // Only users recommended by a recent search can be sent a match request, answering one doesn't need a ticket
+    if let MatchOp::Add = payload.operation {
+        let ticket = payload.ticket.as_deref().ok_or(AppError::InvalidTicket)?;
+        RecommendationTicket::verify(&state.config, ticket, &user_document.id, &payload.target_user_id)?;
+    }
+    let target_document = state.user_store.get_by_id(&payload.target_user_id).await?;
+    // Blocked users are treated as if they didn't exist, so that the block isn't revealed
+    if user_document.is_blocked_with(&target_document) {
+        return Err(AppError::NotFoundError);
+    }
+
+    let record = update_match(&*state.match_store, &user_document.id, &target_document.id, payload.operation).await?;
+
+    Ok(Json(MatchView::new(&record, &user_document, &target_document)))
+}
+
+/// Lists the matches of the user, most recently updated first.
+async fn list_matches(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ListMatchesQuery>,
+) -> Result<Json<MatchViewPage>, AppError> {
+    let user_document =
+        get_user_document(auth_header.token(), &state).await?;
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let page = state
+        .match_store
+        .list_for_user(&user_document.id, query.cursor.as_deref(), limit)
+        .await?;
+
+    Ok(Json(MatchViewPage::load(&*state.user_store, &user_document, page).await?))
+}
+
+/// Routes served by this function, relative to the `/api` prefix.
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/match", post(add_match))
+        .route("/match", get(list_matches))
+        .route("/block", post(moderation::block_user))
+        .route("/report", post(moderation::report_user))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use shared::matches::InMemoryMatchStore;
+    use shared::{MatchPage, Versioned};
+
+    use super::*;
+
+    /// Match store running operations of other users right before the next writes, as if they
+    /// happened between the read and the write of the operation under test.
+    #[derive(Default)]
+    struct InterleavingStore {
+        inner: InMemoryMatchStore,
+        interleaved: Mutex<Vec<(&'static str, &'static str, MatchOp)>>,
+        /// Rewrites the match unchanged before the writes without an operation to run, so that they
+        /// always conflict.
+        touch: bool,
+        /// Writes attempted through this store.
+        writes: Mutex<usize>,
+    }
+
+    impl InterleavingStore {
+        fn interleave(&self, user_id: &'static str, target_id: &'static str, operation: MatchOp) {
+            self.interleaved.lock().unwrap().push((user_id, target_id, operation));
+        }
+
+        async fn run_interleaved(&self, record: &MatchRecord) {
+            *self.writes.lock().unwrap() += 1;
+            let next = self.interleaved.lock().unwrap().pop();
+            if let Some((user_id, target_id, operation)) = next {
+                update_match(&self.inner, user_id, target_id, operation).await.unwrap();
+            } else if self.touch {
+                let [user_id, other_id] = &record.user_ids;
+                if let Some(current) = self.inner.get(user_id, other_id).await.unwrap() {
+                    self.inner.replace_if_match(&current.document, &current.etag).await.unwrap();
+                }
+            }
+        }
+
+        async fn status_for(&self, user_id: &str, other_id: &str) -> MatchStatus {
+            self.inner.get(user_id, other_id).await.unwrap().unwrap().document.status_for(user_id)
+        }
+    }
+
+    #[async_trait]
+    impl MatchStore for InterleavingStore {
+        async fn get(&self, user_id: &str, other_id: &str) -> Result<Option<Versioned<MatchRecord>>, AppError> {
+            self.inner.get(user_id, other_id).await
+        }
+
+        async fn create(&self, record: &MatchRecord) -> Result<(), AppError> {
+            self.run_interleaved(record).await;
+            self.inner.create(record).await
+        }
+
+        async fn replace_if_match(&self, record: &MatchRecord, etag: &str) -> Result<(), AppError> {
+            self.run_interleaved(record).await;
+            self.inner.replace_if_match(record, etag).await
+        }
+
+        async fn list_for_user(&self, user_id: &str, cursor: Option<&str>, limit: usize) -> Result<MatchPage, AppError> {
+            self.inner.list_for_user(user_id, cursor, limit).await
+        }
+    }
+
+    /// A match requested by `alice` to `bob`.
+    async fn requested() -> InterleavingStore {
+        let store = InterleavingStore::default();
+        update_match(&store.inner, "alice", "bob", MatchOp::Add).await.unwrap();
+        store
+    }
+
+    #[tokio::test]
+    async fn accept_during_a_cancel_is_retried_on_the_accepted_match() {
+        let store = requested().await;
+        store.interleave("bob", "alice", MatchOp::Accept);
+
+        let record = update_match(&store, "alice", "bob", MatchOp::Cancel).await.unwrap();
+        assert_eq!(record.status_for("alice"), MatchStatus::Cancelled);
+        // The first write conflicted with the accept, and the retry was applied on top of it
+        assert_eq!(*store.writes.lock().unwrap(), 2);
+        let stored = store.inner.get("alice", "bob").await.unwrap().unwrap();
+        assert_eq!(stored.etag, "2");
+        assert_eq!(stored.document.status_for("bob"), MatchStatus::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn reject_during_a_cancel_is_not_overwritten() {
+        let store = requested().await;
+        store.interleave("bob", "alice", MatchOp::Reject);
+
+        let result = update_match(&store, "alice", "bob", MatchOp::Cancel).await;
+        assert!(matches!(result, Err(AppError::IllegalTransition)), "{:?}", result);
+        assert_eq!(store.status_for("bob", "alice").await, MatchStatus::Denied);
+        assert_eq!(store.status_for("alice", "bob").await, MatchStatus::Denied);
+    }
+
+    #[tokio::test]
+    async fn reject_during_an_accept_is_not_overwritten() {
+        let store = requested().await;
+        // The same user answering from two devices at once
+        store.interleave("bob", "alice", MatchOp::Reject);
+
+        let result = update_match(&store, "bob", "alice", MatchOp::Accept).await;
+        assert!(matches!(result, Err(AppError::IllegalTransition)), "{:?}", result);
+        assert_eq!(store.status_for("alice", "bob").await, MatchStatus::Denied);
+    }
+
+    #[tokio::test]
+    async fn accept_during_a_reject_is_not_overwritten() {
+        let store = requested().await;
+        store.interleave("bob", "alice", MatchOp::Accept);
+
+        let result = update_match(&store, "bob", "alice", MatchOp::Reject).await;
+        assert!(matches!(result, Err(AppError::IllegalTransition)), "{:?}", result);
+        assert_eq!(store.status_for("alice", "bob").await, MatchStatus::Accepted);
+    }
+
+    #[tokio::test]
+    async fn cancel_during_an_accept_is_not_overwritten() {
+        let store = requested().await;
+        // Bob's accept first conflicts with a cancel of Alice, whose request is then gone
+        store.interleave("alice", "bob", MatchOp::Cancel);
+
+        let result = update_match(&store, "bob", "alice", MatchOp::Accept).await;
+        assert!(matches!(result, Err(AppError::IllegalTransition)), "{:?}", result);
+        assert_eq!(store.status_for("bob", "alice").await, MatchStatus::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn requests_of_both_users_at_once_keep_the_first_one() {
+        let store = InterleavingStore::default();
+        store.interleave("bob", "alice", MatchOp::Add);
+
+        // Alice's request conflicts with Bob's, which she can then accept
+        let result = update_match(&store, "alice", "bob", MatchOp::Add).await;
+        assert!(matches!(result, Err(AppError::GenericError)), "{:?}", result);
+        assert_eq!(store.status_for("alice", "bob").await, MatchStatus::AwaitingUserAction);
+        let record = update_match(&store, "alice", "bob", MatchOp::Accept).await.unwrap();
+        assert_eq!(record.status_for("bob"), MatchStatus::Accepted);
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_matches_changing_at_every_attempt() {
+        let store = InterleavingStore {
+            touch: true,
+            ..requested().await
+        };
+
+        let result = update_match(&store, "alice", "bob", MatchOp::Cancel).await;
+        assert!(matches!(result, Err(AppError::Conflict)), "{:?}", result);
+        assert_eq!(*store.writes.lock().unwrap(), shared::store::MAX_UPDATE_ATTEMPTS);
+        assert_eq!(store.status_for("alice", "bob").await, MatchStatus::Pending);
+    }
+}
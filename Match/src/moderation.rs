@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use axum::{extract::State, Json, TypedHeader, headers::{Authorization, authorization::Bearer}};
+use serde::Deserialize;
+use shared::reports::MAX_DETAILS_LENGTH;
+use shared::{get_user_document, retry_on_conflict, update_user, AppError, AppState, IndexAction, IndexActionType, MatchStatus, Report, ReportReason, UserSearchData};
+
+#[derive(Deserialize)]
+pub(crate) struct BlockBody {
+    target_user_id: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ReportBody {
+    target_user_id: String,
+    reason: ReportReason,
+    details: Option<String>,
+}
+
+/// Denies the match between two users, if they have one, whatever its status.
+async fn deny_match(state: &AppState, user_id: &str, target_id: &str) -> Result<(), AppError> {
+    let match_store = &*state.match_store;
+    retry_on_conflict(&format!("Match {} - {}", user_id, target_id), || async move {
+        let Some(mut existing) = match_store.get(user_id, target_id).await? else {
+            return Ok(());
+        };
+        if existing.document.status == MatchStatus::Denied {
+            return Ok(());
+        }
+        existing.document.set_status_for(user_id, MatchStatus::Denied);
+        match_store.replace_if_match(&existing.document, &existing.etag).await
+    })
+    .await
+}
+
+/// Blocks a user. The two users are no longer recommended to each other in either direction,
+/// their match (if any) is denied, and neither can send the other a new match request.
+pub(crate) async fn block_user(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<BlockBody>,
+) -> Result<(), AppError> {
+    let user_document =
+        get_user_document(auth_header.token(), &state).await?;
+    if user_document.id == payload.target_user_id {
+        return Err(AppError::GenericError);
+    }
+    let target_document = state.user_store.get_by_id(&payload.target_user_id).await?;
+
+    let mut newly_blocked = false;
+    let user_document = update_user(&*state.user_store, &user_document.id, |user_document| {
+        newly_blocked = !user_document.blocked_user_ids.contains(&target_document.id);
+        if newly_blocked {
+            user_document.blocked_user_ids.push(target_document.id.clone());
+        }
+        Ok(newly_blocked)
+    })
+    .await?;
+    if newly_blocked {
+        // The index filters out users whose blocklist contains the one searching
+        state.vector_index.index_documents(&[
+            IndexAction {
+                action_type: IndexActionType::Merge,
+                user_document: UserSearchData::from(user_document.clone()),
+            }
+        ]).await?;
+    }
+    deny_match(&state, &user_document.id, &target_document.id).await?;
+
+    println!("User {} blocked {}", user_document.id, target_document.id);
+    Ok(())
+}
+
+/// Reports a user for moderation. Reporting doesn't block the user, clients are expected to offer both.
+pub(crate) async fn report_user(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ReportBody>,
+) -> Result<(), AppError> {
+    let user_document =
+        get_user_document(auth_header.token(), &state).await?;
+    if user_document.id == payload.target_user_id {
+        return Err(AppError::GenericError);
+    }
+    if payload
+        .details
+        .as_ref()
+        .is_some_and(|details| details.chars().count() > MAX_DETAILS_LENGTH)
+    {
+        return Err(AppError::GenericError);
+    }
+    let target_document = state.user_store.get_by_id(&payload.target_user_id).await?;
+
+    let report = Report::new(
+        &user_document.id,
+        &target_document.id,
+        payload.reason,
+        payload.details.filter(|details| !details.trim().is_empty()),
+    );
+    state.report_store.create(&report).await?;
+
+    println!("User {} reported {} for {:?}", user_document.id, target_document.id, report.reason);
+    Ok(())
+}
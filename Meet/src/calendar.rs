@@ -0,0 +1,479 @@
+//! iCalendar (RFC 5545) export of confirmed meetings, one at a time or as a feed calendar apps can
+//! subscribe to. Calendar apps can't send the access token, so the feed is authenticated by an
+//! unguessable token in its URL instead: `<user id>.<random part>`, stored hashed like session tokens.
+
+use std::{collections::BTreeMap, ops::RangeInclusive, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    headers::{authorization::Bearer, Authorization},
+    http::header,
+    response::IntoResponse,
+    Json, TypedHeader,
+};
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+use serde::Serialize;
+use shared::meetings::MAX_LISTED_MEETINGS;
+use shared::session::{generate_token, hash_token};
+use shared::{get_user_document, update_user, AppError, AppState, Meeting, MeetingStatus};
+
+const CONTENT_TYPE: &str = "text/calendar; charset=utf-8";
+/// Length of the random part of feed tokens.
+const FEED_SECRET_LENGTH: usize = 32;
+/// Lines longer than this many bytes are folded, as required by the RFC.
+const MAX_LINE_LENGTH: usize = 75;
+/// Name shown for participants whose account was deleted.
+const DELETED_USER_NAME: &str = "Deleted user";
+/// Most years a time zone is described over, bounding the scan of its changes of offset.
+const MAX_TIMEZONE_YEARS: i32 = 10;
+
+#[derive(Serialize)]
+pub(crate) struct CalendarFeedResponse {
+    token: String,
+    /// Path of the feed, to append to the address of the API.
+    path: String,
+}
+
+/// A confirmed meeting, as seen by one of its participants.
+struct CalendarEvent<'a> {
+    meeting: &'a Meeting,
+    other_user_name: String,
+}
+
+/// Escapes a TEXT value: backslashes, commas, semicolons and newlines.
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Splits a content line in chunks of at most 75 bytes, without cutting characters, each
+/// continuation starting with a space.
+fn fold_line(line: &str, output: &mut String) {
+    let mut length = 0;
+    for character in line.chars() {
+        if length + character.len_utf8() > MAX_LINE_LENGTH {
+            output.push_str("\r\n ");
+            length = 1;
+        }
+        output.push(character);
+        length += character.len_utf8();
+    }
+    output.push_str("\r\n");
+}
+
+fn format_utc(date_time: &DateTime<Utc>) -> String {
+    date_time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn format_local(date_time: &NaiveDateTime) -> String {
+    date_time.format("%Y%m%dT%H%M%S").to_string()
+}
+
+/// UTC offset as `+HHMM`, or `+HHMMSS` for the odd historical offset with seconds.
+fn format_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    match seconds % 60 {
+        0 => format!("{}{:02}{:02}", sign, seconds / 3600, seconds / 60 % 60),
+        rest => format!("{}{:02}{:02}{:02}", sign, seconds / 3600, seconds / 60 % 60, rest),
+    }
+}
+
+/// Time zone of the point of interest of a meeting, if it's known. Events elsewhere are in UTC.
+fn meeting_timezone(meeting: &Meeting) -> Option<Tz> {
+    let name = meeting.poi.timezone.as_deref()?;
+    name.parse()
+        .map_err(|_| println!("Unknown time zone {} of POI {}", name, meeting.poi.id))
+        .ok()
+}
+
+/// The offset in effect at an instant, with whether it's daylight saving time and its abbreviation.
+fn offset_at(tz: Tz, instant: &DateTime<Utc>) -> (i32, bool, String) {
+    let offset = tz.offset_from_utc_datetime(&instant.naive_utc());
+    (
+        offset.fix().local_minus_utc(),
+        !offset.dst_offset().is_zero(),
+        offset.abbreviation().to_owned(),
+    )
+}
+
+/// VTIMEZONE component describing a time zone over whole years. Rather than recurrence rules, which
+/// change over time, each observance is listed with its own start, found by scanning the years.
+/// Only the last [`MAX_TIMEZONE_YEARS`] years are described, older events falling back on the first
+/// observance listed, and the zone is left out if the years can't be represented.
+fn vtimezone(tz: Tz, years: RangeInclusive<i32>, lines: &mut Vec<String>) {
+    let first_year = (*years.start()).max(years.end() - MAX_TIMEZONE_YEARS + 1);
+    let (Some(start), Some(end)) = (
+        Utc.with_ymd_and_hms(first_year, 1, 1, 0, 0, 0).single(),
+        years
+            .end()
+            .checked_add(1)
+            .and_then(|year| Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0).single()),
+    ) else {
+        println!("Can't describe the time zone {} over the years {:?}, leaving it out", tz.name(), years);
+        return;
+    };
+
+    // The first observance covers the start of the range, then one more for each change of offset
+    let mut observances = vec![(start, offset_at(tz, &start), offset_at(tz, &start))];
+    let mut previous = start;
+    while previous < end {
+        let next = previous + Duration::days(1);
+        if offset_at(tz, &next).0 != offset_at(tz, &previous).0 {
+            // Narrows the change down to the minute, since the day started at midnight
+            let (mut before, mut after) = (0, Duration::days(1).num_minutes());
+            while after - before > 1 {
+                let middle = (before + after) / 2;
+                if offset_at(tz, &(previous + Duration::minutes(middle))).0 == offset_at(tz, &previous).0 {
+                    before = middle;
+                } else {
+                    after = middle;
+                }
+            }
+            let onset = previous + Duration::minutes(after);
+            observances.push((onset, offset_at(tz, &previous), offset_at(tz, &onset)));
+        }
+        previous = next;
+    }
+
+    lines.push("BEGIN:VTIMEZONE".to_owned());
+    lines.push(format!("TZID:{}", tz.name()));
+    for (onset, (offset_from, _, _), (offset_to, dst, abbreviation)) in observances {
+        let component = if dst { "DAYLIGHT" } else { "STANDARD" };
+        // The onset is given in the local time in effect before it
+        let local_onset = onset.naive_utc() + Duration::seconds(offset_from.into());
+        lines.push(format!("BEGIN:{}", component));
+        lines.push(format!("DTSTART:{}", format_local(&local_onset)));
+        lines.push(format!("TZOFFSETFROM:{}", format_offset(offset_from)));
+        lines.push(format!("TZOFFSETTO:{}", format_offset(offset_to)));
+        lines.push(format!("TZNAME:{}", escape_text(&abbreviation)));
+        lines.push(format!("END:{}", component));
+    }
+    lines.push("END:VTIMEZONE".to_owned());
+}
+
+/// DTSTART or DTEND property, in the time zone of the place when known.
+fn time_property(name: &str, instant: &DateTime<Utc>, tz: Option<Tz>) -> String {
+    match tz {
+        Some(tz) => format!(
+            "{};TZID={}:{}",
+            name,
+            tz.name(),
+            format_local(&instant.with_timezone(&tz).naive_local())
+        ),
+        None => format!("{}:{}", name, format_utc(instant)),
+    }
+}
+
+fn vevent(event: &CalendarEvent, lines: &mut Vec<String>) {
+    let meeting = event.meeting;
+    let Some(slot) = meeting.confirmed_slot else {
+        return;
+    };
+    let tz = meeting_timezone(meeting);
+    let location = match &meeting.poi.address {
+        Some(address) => format!("{}, {}", meeting.poi.name, address),
+        None => meeting.poi.name.clone(),
+    };
+
+    lines.push("BEGIN:VEVENT".to_owned());
+    lines.push(format!("UID:{}@localink", meeting.id));
+    lines.push(format!("DTSTAMP:{}", format_utc(&meeting.updated_at)));
+    lines.push(time_property("DTSTART", &slot.start, tz));
+    lines.push(time_property("DTEND", &slot.end, tz));
+    // Every change of the meeting is an entry of its history, so calendar apps pick up the latest one
+    lines.push(format!("SEQUENCE:{}", meeting.history.len()));
+    lines.push(format!("SUMMARY:{}", escape_text(&format!("Meeting with {}", event.other_user_name))));
+    lines.push(format!("LOCATION:{}", escape_text(&location)));
+    lines.push(format!(
+        "GEO:{:.6};{:.6}",
+        meeting.poi.location.latitude(),
+        meeting.poi.location.longitude()
+    ));
+    lines.push(format!(
+        "STATUS:{}",
+        match meeting.status {
+            MeetingStatus::Cancelled => "CANCELLED",
+            _ => "CONFIRMED",
+        }
+    ));
+    lines.push("END:VEVENT".to_owned());
+}
+
+/// Renders a calendar with an event per meeting, along with the time zones they take place in.
+fn render_calendar(name: &str, events: &[CalendarEvent]) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        "PRODID:-//LocaLink//Meetings//EN".to_owned(),
+        "CALSCALE:GREGORIAN".to_owned(),
+        "METHOD:PUBLISH".to_owned(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+    ];
+
+    // Each time zone is described over the years of the events taking place in it
+    let mut timezones: BTreeMap<&str, (Tz, RangeInclusive<i32>)> = BTreeMap::new();
+    for event in events {
+        let (Some(tz), Some(slot)) = (meeting_timezone(event.meeting), event.meeting.confirmed_slot) else {
+            continue;
+        };
+        let (first, last) = (slot.start.year(), slot.end.year());
+        timezones
+            .entry(tz.name())
+            .and_modify(|(_, years)| *years = (*years.start()).min(first)..=(*years.end()).max(last))
+            .or_insert((tz, first..=last));
+    }
+    for (tz, years) in timezones.into_values() {
+        vtimezone(tz, years, &mut lines);
+    }
+    for event in events {
+        vevent(event, &mut lines);
+    }
+    lines.push("END:VCALENDAR".to_owned());
+
+    let mut output = String::new();
+    for line in lines {
+        fold_line(&line, &mut output);
+    }
+    output
+}
+
+/// Meetings end up in calendars once confirmed, and stay there when cancelled afterwards so that
+/// calendar apps remove them.
+fn is_scheduled(meeting: &Meeting) -> bool {
+    meeting.confirmed_slot.is_some()
+}
+
+async fn calendar_event<'a>(state: &AppState, meeting: &'a Meeting, user_id: &str) -> Result<CalendarEvent<'a>, AppError> {
+    let other_user_name = match state.user_store.get_by_id(meeting.other_user(user_id)).await {
+        Ok(other_user_document) => other_user_document.name,
+        Err(AppError::NotFoundError) => DELETED_USER_NAME.to_owned(),
+        Err(err) => return Err(err),
+    };
+    Ok(CalendarEvent {
+        meeting,
+        other_user_name,
+    })
+}
+
+/// A single confirmed meeting as an `.ics` file.
+pub(crate) async fn meeting_calendar(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_document = get_user_document(auth_header.token(), &state).await?;
+    let meeting = state.meeting_store.get(&id).await?.document;
+    // Meetings of other users don't exist as far as this user can tell
+    if !meeting.has_participant(&user_document.id) || !is_scheduled(&meeting) {
+        return Err(AppError::NotFoundError);
+    }
+    let event = calendar_event(&state, &meeting, &user_document.id).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, CONTENT_TYPE.to_owned()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"meeting-{}.ics\"", meeting.id)),
+        ],
+        render_calendar("LocaLink meeting", &[event]),
+    ))
+}
+
+/// Creates the calendar feed of the user, or replaces its token, which stops the previous URL from working.
+pub(crate) async fn rotate_calendar_feed(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<CalendarFeedResponse>, AppError> {
+    let user_document = get_user_document(auth_header.token(), &state).await?;
+    let token = format!("{}.{}", user_document.id, generate_token(FEED_SECRET_LENGTH));
+    let token_hash = hash_token(&state.config.token_hash_secret, &token);
+    update_user(&*state.user_store, &user_document.id, |user_document| {
+        user_document.calendar_feed_token_hash = Some(token_hash.clone());
+        Ok(true)
+    })
+    .await?;
+
+    println!("Calendar feed of {} rotated", user_document.id);
+    Ok(Json(CalendarFeedResponse {
+        path: format!("/api/calendar/feed/{}", token),
+        token,
+    }))
+}
+
+pub(crate) async fn delete_calendar_feed(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+) -> Result<(), AppError> {
+    let user_document = get_user_document(auth_header.token(), &state).await?;
+    let mut deleted = false;
+    update_user(&*state.user_store, &user_document.id, |user_document| {
+        deleted = user_document.calendar_feed_token_hash.take().is_some();
+        Ok(deleted)
+    })
+    .await?;
+    if deleted {
+        println!("Calendar feed of {} deleted", user_document.id);
+    }
+    Ok(())
+}
+
+/// The scheduled meetings of the user owning the feed token, for calendar apps to subscribe to.
+pub(crate) async fn calendar_feed(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    // Invalid tokens are reported as missing feeds, so that they can't be told apart from deleted ones
+    let (user_id, _) = token.rsplit_once('.').ok_or(AppError::NotFoundError)?;
+    let user_document = state.user_store.get_by_id(user_id).await?;
+    if user_document.calendar_feed_token_hash != Some(hash_token(&state.config.token_hash_secret, &token)) {
+        return Err(AppError::NotFoundError);
+    }
+
+    let meetings = state
+        .meeting_store
+        .list_for_user(&user_document.id, MAX_LISTED_MEETINGS)
+        .await?;
+    let mut events = Vec::new();
+    for meeting in meetings.iter().filter(|meeting| is_scheduled(meeting)) {
+        events.push(calendar_event(&state, meeting, &user_document.id).await?);
+    }
+
+    Ok((
+        [(header::CONTENT_TYPE, CONTENT_TYPE)],
+        render_calendar("LocaLink meetings", &events),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::{GeoPoint, Poi, PoiCategory, TimeSlot};
+
+    use super::*;
+
+    fn meeting(timezone: Option<&str>) -> Meeting {
+        let poi = Poi {
+            id: "poi".to_owned(),
+            name: "Café, Bar".to_owned(),
+            address: Some("Piazza del Duomo".to_owned()),
+            category: PoiCategory::default(),
+            opening_hours: None,
+            partner: false,
+            location: GeoPoint::new(45.4642, 9.19).unwrap(),
+            timezone: timezone.map(str::to_owned),
+        };
+        let slot = TimeSlot {
+            start: Utc.with_ymd_and_hms(2026, 6, 1, 10, 0, 0).unwrap(),
+            end: Utc.with_ymd_and_hms(2026, 6, 1, 11, 30, 0).unwrap(),
+        };
+        let mut meeting = Meeting::propose("alice", "bob", poi, vec![slot]);
+        meeting.status = MeetingStatus::Confirmed;
+        meeting.confirmed_slot = Some(slot);
+        meeting
+    }
+
+    fn render(meeting: &Meeting) -> String {
+        let event = CalendarEvent {
+            meeting,
+            other_user_name: "Bob".to_owned(),
+        };
+        render_calendar("LocaLink meeting", &[event])
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(escape_text("a,b;c\\d\ne\r\nf"), "a\\,b\\;c\\\\d\\ne\\nf");
+    }
+
+    #[test]
+    fn long_lines_are_folded_without_cutting_characters() {
+        let line = format!("SUMMARY:{}", "é€".repeat(40));
+        let mut output = String::new();
+        fold_line(&line, &mut output);
+
+        assert!(output.ends_with("\r\n"));
+        let physical: Vec<&str> = output.trim_end_matches("\r\n").split("\r\n").collect();
+        assert!(physical.len() > 1);
+        assert!(physical.iter().all(|part| part.len() <= MAX_LINE_LENGTH), "{:?}", physical);
+        assert!(physical[1..].iter().all(|part| part.starts_with(' ')));
+        assert_eq!(output.trim_end_matches("\r\n").replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn short_lines_are_left_alone() {
+        let mut output = String::new();
+        fold_line("VERSION:2.0", &mut output);
+        assert_eq!(output, "VERSION:2.0\r\n");
+    }
+
+    #[test]
+    fn offsets_are_formatted() {
+        assert_eq!(format_offset(3600), "+0100");
+        assert_eq!(format_offset(-16200), "-0430");
+        assert_eq!(format_offset(0), "+0000");
+        // Rome before standard time, 49 minutes and 56 seconds ahead of UTC
+        assert_eq!(format_offset(2996), "+004956");
+    }
+
+    #[test]
+    fn daylight_saving_changes_are_listed() {
+        let mut lines = Vec::new();
+        vtimezone(chrono_tz::Europe::Rome, 2026..=2026, &mut lines);
+        let expected = [
+            "BEGIN:VTIMEZONE",
+            "TZID:Europe/Rome",
+            "BEGIN:STANDARD",
+            "DTSTART:20260101T010000",
+            "TZOFFSETFROM:+0100",
+            "TZOFFSETTO:+0100",
+            "TZNAME:CET",
+            "END:STANDARD",
+            "BEGIN:DAYLIGHT",
+            "DTSTART:20260329T020000",
+            "TZOFFSETFROM:+0100",
+            "TZOFFSETTO:+0200",
+            "TZNAME:CEST",
+            "END:DAYLIGHT",
+            "BEGIN:STANDARD",
+            "DTSTART:20261025T030000",
+            "TZOFFSETFROM:+0200",
+            "TZOFFSETTO:+0100",
+            "TZNAME:CET",
+            "END:STANDARD",
+            "END:VTIMEZONE",
+        ];
+        assert_eq!(lines, expected);
+    }
+
+    #[test]
+    fn long_ranges_of_years_are_clamped() {
+        let mut lines = Vec::new();
+        vtimezone(chrono_tz::Europe::Rome, 1990..=2026, &mut lines);
+        let first_start = lines.iter().find(|line| line.starts_with("DTSTART:")).unwrap();
+        assert_eq!(first_start, &format!("DTSTART:{}0101T010000", 2026 - MAX_TIMEZONE_YEARS + 1));
+        // A change of offset in spring and autumn of each year
+        assert_eq!(lines.iter().filter(|line| *line == "BEGIN:DAYLIGHT").count(), MAX_TIMEZONE_YEARS as usize);
+    }
+
+    #[test]
+    fn times_are_local_to_the_place_when_its_time_zone_is_known() {
+        let calendar = render(&meeting(Some("Europe/Rome")));
+        assert!(calendar.contains("\r\nBEGIN:VTIMEZONE\r\nTZID:Europe/Rome\r\n"), "{}", calendar);
+        assert!(calendar.contains("\r\nDTSTART;TZID=Europe/Rome:20260601T120000\r\n"), "{}", calendar);
+        assert!(calendar.contains("\r\nDTEND;TZID=Europe/Rome:20260601T133000\r\n"), "{}", calendar);
+        assert!(calendar.contains("\r\nLOCATION:Café\\, Bar\\, Piazza del Duomo\r\n"), "{}", calendar);
+    }
+
+    #[test]
+    fn times_are_in_utc_otherwise() {
+        for timezone in [None, Some("Not/A_Zone")] {
+            let calendar = render(&meeting(timezone));
+            assert!(!calendar.contains("VTIMEZONE"), "{}", calendar);
+            assert!(calendar.contains("\r\nDTSTART:20260601T100000Z\r\n"), "{}", calendar);
+            assert!(calendar.contains("\r\nDTEND:20260601T113000Z\r\n"), "{}", calendar);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    headers::{authorization::Bearer, Authorization},
+    Json, TypedHeader,
+};
+use serde::{Deserialize, Serialize};
+use shared::meetups::{MAX_GROUP_SIZE, MAX_LISTED_MEETUPS};
+use shared::pois::selection::{
+    shortlist, MeetingPointCandidate, MeetingStrategy, Participant, DEFAULT_SHORTLIST_SIZE,
+    MAX_SHORTLIST_SIZE,
+};
+use shared::{get_user_document, AppError, AppState, Meetup};
+
+use crate::accepted_match_user;
+
+#[derive(Deserialize)]
+pub(crate) struct GroupMeetBody {
+    /// Users to meet, each one must have accepted a match with the organizer.
+    target_ids: Vec<String>,
+    /// How the meeting points are ranked, the nearest to the center of the participants by default.
+    #[serde(default)]
+    strategy: MeetingStrategy,
+    /// Number of meeting points returned, 3 by default.
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct GroupMeetResponse {
+    /// Shared with every participant, with the best meeting point.
+    meetup: Meetup,
+    /// Only returned to the organizer, since they reveal how far each participant is.
+    candidates: Vec<MeetingPointCandidate>,
+}
+
+/// Chooses a meeting point for the organizer and several of their accepted matches.
+/// The participants don't need to be matched with each other, but none can have blocked another.
+pub(crate) async fn create_meetup(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<GroupMeetBody>,
+) -> Result<Json<GroupMeetResponse>, AppError> {
+    let user_document = get_user_document(auth_header.token(), &state).await?;
+    let mut target_ids = payload.target_ids;
+    target_ids.sort();
+    target_ids.dedup();
+    if target_ids.is_empty()
+        || target_ids.len() > MAX_GROUP_SIZE
+        || target_ids.contains(&user_document.id)
+    {
+        return Err(AppError::GenericError);
+    }
+
+    let mut documents = vec![user_document];
+    for target_id in &target_ids {
+        documents.push(accepted_match_user(&state, &documents[0], target_id).await?);
+    }
+    // Blocked users are treated as if they didn't exist, so that the block isn't revealed
+    for (index, document) in documents.iter().enumerate() {
+        if documents[index + 1..].iter().any(|other| document.is_blocked_with(other)) {
+            return Err(AppError::NotFoundError);
+        }
+    }
+    let participants = documents
+        .iter()
+        .map(|document| {
+            Ok(Participant {
+                user_id: document.id.clone(),
+                location: document.location.ok_or(AppError::MissingLocationData)?,
+            })
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+    let limit = payload.limit.unwrap_or(DEFAULT_SHORTLIST_SIZE).clamp(1, MAX_SHORTLIST_SIZE);
+
+    let candidates = shortlist(&*state.poi_store, &participants, payload.strategy.strategy(), limit).await?;
+    let poi = candidates.first().ok_or(AppError::NotFoundError)?.poi.clone();
+    let meetup = Meetup::new(
+        &documents[0].id,
+        participants.into_iter().map(|participant| participant.user_id).collect(),
+        poi,
+    );
+    state.meetup_store.create(&meetup).await?;
+
+    println!(
+        "Meetup {} of {} users at {} chosen with {:?}",
+        meetup.id,
+        meetup.participant_ids.len(),
+        meetup.poi.id,
+        payload.strategy
+    );
+    Ok(Json(GroupMeetResponse { meetup, candidates }))
+}
+
+/// The latest meetups the user takes part in, whether they organized them or not.
+pub(crate) async fn list_meetups(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<Meetup>>, AppError> {
+    let user_document = get_user_document(auth_header.token(), &state).await?;
+    Ok(Json(
+        state
+            .meetup_store
+            .list_for_user(&user_document.id, MAX_LISTED_MEETUPS)
+            .await?,
+    ))
+}
+
+pub(crate) async fn get_meetup(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<Meetup>, AppError> {
+    let user_document = get_user_document(auth_header.token(), &state).await?;
+    let meetup = state.meetup_store.get(&id).await?;
+    // Meetups of other users don't exist as far as this user can tell
+    if !meetup.has_participant(&user_document.id) {
+        return Err(AppError::NotFoundError);
+    }
+    Ok(Json(meetup))
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use axum::{extract::State, routing::{delete, get, post}, Json, Router, TypedHeader, headers::{Authorization, authorization::Bearer}};
+use serde::{Deserialize, Serialize};
+use shared::pois::selection::{shortlist, MeetingPointCandidate, MeetingStrategy, Participant, DEFAULT_SHORTLIST_SIZE, MAX_SHORTLIST_SIZE};
+use shared::{get_user_document, location_precision, AppError, AppState, LocationPrecision, Poi, UserDocument};
+
+mod calendar;
+mod group;
+mod meetings;
+mod pois;
+
+#[derive(Deserialize)]
+struct MeetBody {
+    target_id: String,
+    /// How the meeting points are ranked, the nearest to the midpoint between the users by default.
+    #[serde(default)]
+    strategy: MeetingStrategy,
+    /// Number of meeting points returned, 3 by default.
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, Debug)]
+struct MeetResponse {
+    /// The best meeting point, also the first candidate.
+    poi: Poi,
+    candidates: Vec<MeetingPointCandidate>,
+}
+
+/// Loads a user the given one may see the exact location of, i.e. an accepted match neither of them
+/// blocked. Other users are reported as missing, so that nothing is revealed about them.
+pub(crate) async fn accepted_match_user(
+    state: &AppState,
+    user_document: &UserDocument,
+    target_id: &str,
+) -> Result<UserDocument, AppError> {
+    let Some(existing) = state.match_store.get(&user_document.id, target_id).await? else {
+        return Err(AppError::NotFoundError);
+    };
+    let target_user_document = state.user_store.get_by_id(target_id).await?;
+    if location_precision(user_document, &target_user_document, Some(&existing.document)) != LocationPrecision::Exact {
+        return Err(AppError::NotFoundError);
+    }
+    Ok(target_user_document)
+}
+
+/// Meeting points for two users, best first.
+pub(crate) async fn suggest_meeting_points(
+    state: &AppState,
+    user_document: &UserDocument,
+    target_user_document: &UserDocument,
+    strategy: MeetingStrategy,
+    limit: usize,
+) -> Result<Vec<MeetingPointCandidate>, AppError> {
+    let (Some(user_location), Some(target_location)) = (user_document.location, target_user_document.location) else {
+        return Err(AppError::MissingLocationData);
+    };
+    let participants = [
+        Participant { user_id: user_document.id.clone(), location: user_location },
+        Participant { user_id: target_user_document.id.clone(), location: target_location },
+    ];
+    shortlist(&*state.poi_store, &participants, strategy.strategy(), limit).await
+}
+
+async fn meet(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<MeetBody>,
+) -> Result<Json<MeetResponse>, AppError> {
+    println!("Called with {}", payload.target_id);
+    let user_document =
+        get_user_document(auth_header.token(), &state).await?;
+    let target_user_document = accepted_match_user(&state, &user_document, &payload.target_id).await?;
+
+    let limit = payload.limit.unwrap_or(DEFAULT_SHORTLIST_SIZE).clamp(1, MAX_SHORTLIST_SIZE);
+    let candidates = suggest_meeting_points(&state, &user_document, &target_user_document, payload.strategy, limit).await?;
+    let poi = candidates.first().ok_or(AppError::NotFoundError)?.poi.clone();
+
+    println!("Point chosen with {:?}: {} at {:?}", payload.strategy, poi.id, poi.location);
+    Ok(Json(MeetResponse{poi, candidates}))
+}
+
+/// Routes served by this function, relative to the `/api` prefix.
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/meet", post(meet))
+        .route("/meet/group", post(group::create_meetup).get(group::list_meetups))
+        .route("/meet/group/:id", get(group::get_meetup))
+        .route("/meetings", post(meetings::propose_meeting).get(meetings::list_meetings))
+        .route("/meetings/:id", get(meetings::get_meeting).post(meetings::update_meeting))
+        .route("/meetings/:id/calendar", get(calendar::meeting_calendar))
+        .route("/calendar/feed", post(calendar::rotate_calendar_feed).delete(calendar::delete_calendar_feed))
+        .route("/calendar/feed/:token", get(calendar::calendar_feed))
+        .route("/pois", get(pois::nearby_pois))
+        .route("/manage/pois", get(pois::list_pois).post(pois::upsert_poi))
+        .route("/manage/pois/import", post(pois::import_pois))
+        .route("/manage/pois/:id", delete(pois::delete_poi))
+}
@@ -0,0 +1,139 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    headers::{authorization::Bearer, Authorization},
+    Json, TypedHeader,
+};
+use serde::Deserialize;
+use shared::meetings::{MeetingChange, MAX_LISTED_MEETINGS};
+use shared::{get_user_document, retry_on_conflict, AppError, AppState, Meeting, Poi, TimeSlot, UserDocument};
+
+use crate::{accepted_match_user, suggest_meeting_points};
+
+#[derive(Deserialize)]
+pub(crate) struct ProposeMeetingBody {
+    target_id: String,
+    /// Place to meet at, the one suggested by `/meet` when missing.
+    poi_id: Option<String>,
+    slots: Vec<TimeSlot>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub(crate) enum UpdateMeetingBody {
+    /// Accepts the slot at this index of the current proposal.
+    Accept { slot: usize },
+    Decline,
+    CounterPropose {
+        /// Place to meet at, the one suggested by `/meet` when missing.
+        poi_id: Option<String>,
+        slots: Vec<TimeSlot>,
+    },
+    Cancel,
+}
+
+/// The point of interest with the given id, or the best meeting point for the two users.
+async fn proposed_poi(
+    state: &AppState,
+    poi_id: Option<&str>,
+    user_document: &UserDocument,
+    target_user_document: &UserDocument,
+) -> Result<Poi, AppError> {
+    match poi_id {
+        Some(poi_id) => state.poi_store.get(poi_id).await,
+        None => Ok(suggest_meeting_points(state, user_document, target_user_document, Default::default(), 1)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(AppError::NotFoundError)?
+            .poi),
+    }
+}
+
+/// Proposes a place and some time slots to meet at to an accepted match.
+pub(crate) async fn propose_meeting(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ProposeMeetingBody>,
+) -> Result<Json<Meeting>, AppError> {
+    let user_document = get_user_document(auth_header.token(), &state).await?;
+    let target_user_document = accepted_match_user(&state, &user_document, &payload.target_id).await?;
+    TimeSlot::check_all(&payload.slots)?;
+    let poi = proposed_poi(&state, payload.poi_id.as_deref(), &user_document, &target_user_document).await?;
+
+    let meeting = Meeting::propose(&user_document.id, &target_user_document.id, poi, payload.slots);
+    state.meeting_store.create(&meeting).await?;
+    println!("Meeting {} proposed at {}", meeting.id, meeting.poi.id);
+    Ok(Json(meeting))
+}
+
+/// The latest meetings of the user, most recently updated first.
+pub(crate) async fn list_meetings(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<Meeting>>, AppError> {
+    let user_document = get_user_document(auth_header.token(), &state).await?;
+    Ok(Json(
+        state
+            .meeting_store
+            .list_for_user(&user_document.id, MAX_LISTED_MEETINGS)
+            .await?,
+    ))
+}
+
+pub(crate) async fn get_meeting(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<Meeting>, AppError> {
+    let user_document = get_user_document(auth_header.token(), &state).await?;
+    let meeting = state.meeting_store.get(&id).await?.document;
+    // Meetings of other users don't exist as far as this user can tell
+    if !meeting.has_participant(&user_document.id) {
+        return Err(AppError::NotFoundError);
+    }
+    Ok(Json(meeting))
+}
+
+/// Answers or cancels a meeting. Accepting and counter-proposing still require an accepted match,
+/// while declining and cancelling are always possible. The meeting is written only if it didn't
+/// change since it was read, so concurrent answers are retried on its latest state.
+pub(crate) async fn update_meeting(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(payload): Json<UpdateMeetingBody>,
+) -> Result<Json<Meeting>, AppError> {
+    let user_document = get_user_document(auth_header.token(), &state).await?;
+    let existing = state.meeting_store.get(&id).await?.document;
+    if !existing.has_participant(&user_document.id) {
+        return Err(AppError::NotFoundError);
+    }
+    let change = match payload {
+        UpdateMeetingBody::Accept { slot } => {
+            accepted_match_user(&state, &user_document, existing.other_user(&user_document.id)).await?;
+            MeetingChange::Accept(slot)
+        }
+        UpdateMeetingBody::Decline => MeetingChange::Decline,
+        UpdateMeetingBody::CounterPropose { poi_id, slots } => {
+            let target_user_document =
+                accepted_match_user(&state, &user_document, existing.other_user(&user_document.id)).await?;
+            let poi = proposed_poi(&state, poi_id.as_deref(), &user_document, &target_user_document).await?;
+            MeetingChange::CounterPropose { poi, slots }
+        }
+        UpdateMeetingBody::Cancel => MeetingChange::Cancel,
+    };
+
+    let (meeting_store, id, user_id, change) =
+        (&*state.meeting_store, id.as_str(), user_document.id.as_str(), &change);
+    retry_on_conflict(&format!("Meeting {}", id), || async move {
+        let mut meeting = meeting_store.get(id).await?;
+        meeting.document.apply(user_id, change.clone())?;
+        meeting_store
+            .replace_if_match(&meeting.document, &meeting.etag)
+            .await
+            .map(|_| Json(meeting.document))
+    })
+    .await
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    headers::{authorization::Bearer, Authorization},
+    Json, TypedHeader,
+};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use shared::pois::{
+    import::{self, PoiFormat},
+    DEFAULT_POI_RADIUS_KM, DEFAULT_POI_RESULTS, MAX_POI_RADIUS_KM, MAX_POI_RESULTS,
+};
+use shared::session::hash_token;
+use shared::{get_user_document, AppError, AppState, GeoPoint, NearbyPoi, Poi};
+
+#[derive(Deserialize)]
+pub(crate) struct NearbyQuery {
+    /// Location to search around, the one of the user when missing.
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    radius_km: Option<f64>,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ImportQuery {
+    format: PoiFormat,
+}
+
+/// Points of interest around a location, nearest first.
+pub(crate) async fn nearby_pois(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<NearbyQuery>,
+) -> Result<Json<Vec<NearbyPoi>>, AppError> {
+    let user_document = get_user_document(auth_header.token(), &state).await?;
+    let center = match (query.latitude, query.longitude) {
+        (Some(latitude), Some(longitude)) => GeoPoint::new(latitude, longitude)?,
+        (None, None) => user_document.location.ok_or(AppError::MissingLocationData)?,
+        _ => return Err(AppError::InvalidCoordinates),
+    };
+    let radius_km = query
+        .radius_km
+        .filter(|radius_km| radius_km.is_finite())
+        .unwrap_or(DEFAULT_POI_RADIUS_KM)
+        .clamp(0., MAX_POI_RADIUS_KM);
+    let limit = query.limit.unwrap_or(DEFAULT_POI_RESULTS).clamp(1, MAX_POI_RESULTS);
+
+    Ok(Json(state.poi_store.nearest(&center, radius_km, limit).await?))
+}
+
+/// Only lets through requests bearing the admin API key. Keys are compared through their hashes,
+/// so that the comparison doesn't leak how much of the key was right.
+fn check_admin_key(state: &AppState, auth_header: &TypedHeader<Authorization<Bearer>>) -> Result<(), AppError> {
+    let admin_api_key = state.config.admin_api_key.as_deref().ok_or(AppError::NotFoundError)?;
+    let secret = &state.config.token_hash_secret;
+    if hash_token(secret, auth_header.token()) != hash_token(secret, admin_api_key) {
+        return Err(AppError::AuthError);
+    }
+    Ok(())
+}
+
+/// Catches what the formats alone can't, before anything is saved.
+fn check_poi(poi: &Poi) -> Result<(), AppError> {
+    let unknown_timezone = poi
+        .timezone
+        .as_deref()
+        .is_some_and(|timezone| timezone.parse::<Tz>().is_err());
+    if poi.id.is_empty() || poi.name.is_empty() || unknown_timezone {
+        println!("Invalid POI {:?}", poi);
+        return Err(AppError::InvalidPoiData);
+    }
+    Ok(())
+}
+
+pub(crate) async fn list_pois(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<Poi>>, AppError> {
+    check_admin_key(&state, &auth_header)?;
+    Ok(Json(state.poi_store.list().await?))
+}
+
+/// Creates a point of interest, or replaces the one with the same id.
+pub(crate) async fn upsert_poi(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Json(poi): Json<Poi>,
+) -> Result<Json<Poi>, AppError> {
+    check_admin_key(&state, &auth_header)?;
+    check_poi(&poi)?;
+    state.poi_store.upsert(&poi).await?;
+    println!("Saved POI {}", poi.id);
+    Ok(Json(poi))
+}
+
+pub(crate) async fn delete_poi(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<(), AppError> {
+    check_admin_key(&state, &auth_header)?;
+    state.poi_store.delete(&id).await?;
+    println!("Deleted POI {}", id);
+    Ok(())
+}
+
+/// Imports a GeoJSON or CSV file sent as the request body, in the same formats as `POI_FILE`.
+/// POIs with the id of an existing one replace it.
+pub(crate) async fn import_pois(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ImportQuery>,
+    body: String,
+) -> Result<Json<Vec<Poi>>, AppError> {
+    check_admin_key(&state, &auth_header)?;
+    let pois = import::parse(&body, query.format)?;
+    pois.iter().try_for_each(check_poi)?;
+    for poi in &pois {
+        state.poi_store.upsert(poi).await?;
+    }
+    println!("Imported {} POIs", pois.len());
+    Ok(Json(pois))
+}
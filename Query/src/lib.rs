@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    headers::{authorization::Bearer, Authorization},
+    routing::post,
+    Json, Router, TypedHeader,
+};
+use serde::{Deserialize, Serialize};
+use shared::index::{DEFAULT_SEARCH_RESULTS, MAX_SEARCH_DEPTH, MAX_SEARCH_RADIUS_KM, MAX_SEARCH_RESULTS, MIN_SEARCH_RADIUS_KM};
+use shared::extract::OptionalJson;
+use shared::matches::list_all_for_user;
+use shared::{get_user_document, AppError, AppState, MatchStatus, PublicProfile, RecommendationTicket, UserSearchData, VectorSearch};
+
+/// Options of a search, all optional. Values out of the server-side limits are clamped.
+/// Users the searching user has a match with are excluded from the results, unless asked otherwise.
+/// Denied matches are always excluded.
+#[derive(Deserialize, Default)]
+struct SearchBody {
+    /// Maximum distance of the results, 5 km by default.
+    radius_km: Option<f64>,
+    /// Number of results per page, 3 by default.
+    page_size: Option<usize>,
+    /// `next_cursor` of the previous page, to get the following one.
+    cursor: Option<String>,
+    /// Minimum similarity score of the results, between 0 and 1, or the request is refused.
+    min_score: Option<f32>,
+    /// Keeps users with a match request waiting for an answer, in either direction.
+    #[serde(default)]
+    include_pending: bool,
+    #[serde(default)]
+    include_accepted: bool,
+    #[serde(default)]
+    include_cancelled: bool,
+}
+
+impl SearchBody {
+    /// Whether users with a match in this status can show up in the results.
+    fn includes(&self, status: &MatchStatus) -> bool {
+        match status {
+            MatchStatus::Pending | MatchStatus::AwaitingUserAction => self.include_pending,
+            MatchStatus::Accepted => self.include_accepted,
+            MatchStatus::Cancelled => self.include_cancelled,
+            MatchStatus::Denied => false,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SearchResponse {
+    data: Recommendations,
+    /// Cursor of the next page of results, if there's one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+/// Kept in the same shape as the Cognitive Search response, which older clients read directly.
+#[derive(Serialize)]
+struct Recommendations {
+    value: Vec<RecommendedUser>,
+}
+
+#[derive(Serialize)]
+struct RecommendedUser {
+    #[serde(rename = "@search.score")]
+    search_score: f32,
+    #[serde(flatten)]
+    profile: PublicProfile,
+    /// To send along a match request to this user.
+    ticket: String,
+}
+
+async fn search(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    OptionalJson(payload): OptionalJson<SearchBody>,
+) -> Result<Json<SearchResponse>, AppError> {
+    let user_document = get_user_document(auth_header.token(), &state).await?;
+    println!("Executing vector search...");
+    let user_id = user_document.id.clone();
+    let mut vector_search = VectorSearch::for_user(&UserSearchData::from(user_document))?;
+    let payload = payload.unwrap_or_default();
+    let page_size = payload.page_size.unwrap_or(DEFAULT_SEARCH_RESULTS).clamp(1, MAX_SEARCH_RESULTS);
+    let offset = match payload.cursor.as_deref().map(str::parse::<usize>) {
+        None => 0,
+        Some(Ok(offset)) if offset < MAX_SEARCH_DEPTH => offset,
+        Some(_) => {
+            println!("Invalid search cursor: {:?}", payload.cursor);
+            return Err(AppError::BadRequest);
+        }
+    };
+    if let Some(min_score) = payload.min_score.filter(|min_score| !(0. ..=1.).contains(min_score)) {
+        println!("Invalid minimum score: {}", min_score);
+        return Err(AppError::BadRequest);
+    }
+    if let Some(radius_km) = payload.radius_km.filter(|radius_km| radius_km.is_finite()) {
+        vector_search.radius_km = radius_km.clamp(MIN_SEARCH_RADIUS_KM, MAX_SEARCH_RADIUS_KM);
+    }
+    vector_search.min_score = payload.min_score;
+    vector_search.offset = offset;
+    // One more result than the page size tells if there's a next page
+    vector_search.k = page_size.min(MAX_SEARCH_DEPTH - offset) + 1;
+    // Users already matched with are left out, so that each search brings new people. Denied matches
+    // are stored once for both users, so this also hides them from each other.
+    let matches = list_all_for_user(&*state.match_store, &user_id).await?;
+    vector_search.exclude_ids.extend(
+        matches
+            .iter()
+            .filter(|record| !payload.includes(&record.status))
+            .map(|record| record.other_user(&user_id).to_owned()),
+    );
+    let mut query_response = state.vector_index.search(&vector_search).await?;
+    let next_cursor = (query_response.value.len() > page_size && offset + page_size < MAX_SEARCH_DEPTH)
+        .then(|| (offset + page_size).to_string());
+    query_response.value.truncate(page_size);
+
+    let value = query_response
+        .value
+        .into_iter()
+        .map(|result| RecommendedUser {
+            search_score: result.search_score,
+            ticket: RecommendationTicket::issue(&state.config, &user_id, &result.id),
+            profile: PublicProfile {
+                id: result.id,
+                name: result.name,
+                description: result.description,
+            },
+        })
+        .collect();
+    Ok(Json(SearchResponse {
+        data: Recommendations { value },
+        next_cursor,
+    }))
+}
+
+/// Routes served by this function, relative to the `/api` prefix.
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/query", post(search))
+}
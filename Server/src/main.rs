@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use axum::Router;
+use shared::config::UserStoreConfig;
+use shared::pois::import::import_file;
+use shared::{serve, AppState, Config};
+
+/// Runs every function in a single process, for local development or deployments on a plain VM.
+/// All routers share the same state, so e.g. the in-memory stores work across functions.
+///
+/// `localink-server migrate` upgrades the stored data to the current layout and exits instead.
+/// `localink-server import-pois [file]` imports points of interest from the file (`POI_FILE` by
+/// default) into the configured store and exits, replacing the ones with the same id.
+#[tokio::main]
+async fn main() {
+    let config = Config::load_or_exit();
+    match std::env::args().nth(1).as_deref() {
+        Some("migrate") => {
+            if let Err(err) = shared::migrations::run(&config).await {
+                eprintln!("Migration failed: {:?}", err);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("import-pois") => {
+            let Some(path) = std::env::args().nth(2).or_else(|| config.poi_file.clone()) else {
+                eprintln!("Usage: localink-server import-pois <file>, or set POI_FILE");
+                std::process::exit(1);
+            };
+            let poi_store = shared::get_poi_store(&config.user_store).await;
+            match import_file(&*poi_store, &path).await {
+                Ok(imported) => println!("Imported {} POIs from {}", imported, path),
+                Err(err) => {
+                    eprintln!("Import of {} failed: {:?}", path, err);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        _ => {}
+    }
+    let address = config.server_address;
+    let poi_file = config.poi_file.clone();
+    let in_memory = matches!(config.user_store, UserStoreConfig::Memory);
+    let shared_state = Arc::new(AppState::new(config).await);
+    // The in-memory store starts empty every time, the others are filled once with `import-pois`
+    if let Some(path) = poi_file.filter(|_| in_memory) {
+        match import_file(&*shared_state.poi_store, &path).await {
+            Ok(imported) => println!("Imported {} POIs from {}", imported, path),
+            Err(err) => println!("Could not import POIs from {}: {:?}", path, err),
+        }
+    }
+
+    let api = Router::new()
+        .merge(auth_handler::router())
+        .merge(generate_embeddings::router())
+        .merge(match_handler::router())
+        .merge(meet::router())
+        .merge(query::router())
+        .merge(sync_position::router());
+
+    let app = Router::new().nest("/api", api).with_state(shared_state);
+
+    serve(app, address).await;
+}
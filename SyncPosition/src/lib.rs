@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use axum::{extract::State, routing::post, Json, Router, TypedHeader, headers::{Authorization, authorization::Bearer}};
+use serde::Deserialize;
+use shared::{get_user_document, update_user, AppError, AppState, GeoPoint, IndexAction, UserSearchData};
+
+#[derive(Deserialize)]
+struct SyncPositionBody {
+    latitude: f64,
+    longitude: f64,
+}
+
+async fn sync_position(
+    auth_header: TypedHeader<Authorization<Bearer>>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<SyncPositionBody>
+) -> Result<(), AppError> {
+    let user_document =
+        get_user_document(auth_header.token(), &state).await?;
+
+    // NaN and out of range coordinates are rejected
+    let location = GeoPoint::new(payload.latitude, payload.longitude)?;
+    let user_document = update_user(&*state.user_store, &user_document.id, |user_document| {
+        user_document.location = Some(location);
+        Ok(true)
+    })
+    .await?;
+
+    state.vector_index.index_documents(&[
+        IndexAction {
+            action_type: shared::IndexActionType::Merge,
+            user_document: UserSearchData::from(user_document)
+        }
+    ]).await?;
+    Ok(())
+}
+
+/// Routes served by this function, relative to the `/api` prefix.
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/sync_position", post(sync_position))
+}
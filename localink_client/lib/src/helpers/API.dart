@@ -153,15 +153,14 @@ class API {
     }
   }
 
-  Future<void> match(String token, MatchOp op, String userID, String userName,
-      String userDescription) async {
+  Future<void> match(String token, MatchOp op, String userID,
+      {String? ticket}) async {
     print("HTTPing $addMatchUrl with token $token");
 
     Map data = {
       'operation': op.name,
       'target_user_id': userID,
-      'target_user_name': userName,
-      'target_description': userDescription
+      if (ticket != null) 'ticket': ticket
     };
     //encode Map to JSON
     var body = json.encode(data);
@@ -212,12 +211,14 @@ class UserInfo {
   final String name;
   final String description;
   final double searchScore;
+  final String ticket;
 
   UserInfo({
     required this.id,
     required this.name,
     required this.description,
-    required this.searchScore
+    required this.searchScore,
+    required this.ticket
   });
 
   factory UserInfo.fromJson(Map<String, dynamic> json) {
@@ -225,7 +226,8 @@ class UserInfo {
       id: json['id'],
       name: utf8.decode(json['name'].codeUnits),
       description: utf8.decode(json['description'].codeUnits),
-      searchScore: json['@search.score']
+      searchScore: json['@search.score'],
+      ticket: json['ticket']
     );
   }
 }
@@ -233,21 +235,51 @@ class UserInfo {
 enum MatchOp { Add, Accept, Reject }
 
 class MeetResponse {
-  final Point poi;
+  final Poi poi;
 
   MeetResponse(this.poi);
 
   factory MeetResponse.fromJson(Map<String, dynamic> json) {
-    return MeetResponse(Point.fromJson(json['poi']));
+    return MeetResponse(Poi.fromJson(json['poi']));
   }
 }
 
+/// Point of interest suggested as a meeting point.
+class Poi {
+  final String id;
+  final String name;
+  final String? address;
+  final String category;
+  final String? openingHours;
+  final bool partner;
+  final Point location;
+
+  Poi(this.id, this.name, this.address, this.category, this.openingHours,
+      this.partner, this.location);
+
+  factory Poi.fromJson(Map<String, dynamic> json) {
+    return Poi(
+      json['id'],
+      json['name'],
+      json['address'],
+      json['category'],
+      json['opening_hours'],
+      json['partner'],
+      Point.fromJson(json['location']),
+    );
+  }
+}
+
+/// GeoJSON point, the longitude comes first.
 class Point {
   final String type;
   final List<double> coordinates;
 
   Point(this.type, this.coordinates);
 
+  double get longitude => coordinates[0];
+  double get latitude => coordinates[1];
+
   factory Point.fromJson(Map<String, dynamic> json) {
     return Point(json['type'], json['coordinates'].cast<double>());
   }
@@ -73,7 +73,7 @@ class MapPageState extends State<MapPage> {
 
   Future<void> setupMarkers(String token) async {
     var meet = await API().meet(token, widget.match.userID);
-    _targetPosition = LatLng(meet.poi.coordinates[0], meet.poi.coordinates[1]);
+    _targetPosition = LatLng(meet.poi.location.latitude, meet.poi.location.longitude);
     await setupDeviceMarker();
   }
 
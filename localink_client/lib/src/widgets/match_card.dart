@@ -23,14 +23,14 @@ class MatchCard extends StatelessWidget {
 
   Future<void> accept(String token) async {
     // 1) invoke a function to write the accepted status on cosmos db
-    await API().match(token, MatchOp.Accept, id, name, description);
+    await API().match(token, MatchOp.Accept, id);
     updateItem(MatchStatus.Accepted);
     // 2) update local status so that the widget refreshes
   }
 
   Future<void> reject(String token) async {
     // 1) invoke a function to write the accepted status on cosmos db
-    await API().match(token, MatchOp.Reject, id, name, description);
+    await API().match(token, MatchOp.Reject, id);
     // 2) update local status so that the widget refreshes
     updateItem(MatchStatus.Denied);
   }
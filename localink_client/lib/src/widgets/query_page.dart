@@ -32,7 +32,7 @@ class QueryPageState extends State<QueryPage> {
   Future<void> addMatch(
       String token, UserInfo item, BuildContext context) async {
     widget.authResponse.addMatch(item.id, item.name, item.description);
-    return await API().match(token, MatchOp.Add, item.id, item.name, item.description);
+    return await API().match(token, MatchOp.Add, item.id, ticket: item.ticket);
   }
 
   Future<void> onCardTap(
@@ -8,6 +8,11 @@ tag="localink"
 account="localink-account-cosmos" #needs to be lower case
 database="main"
 user_container="users"
+match_container="matches"
+report_container="reports"
+poi_container="pois"
+meetup_container="meetups"
+meeting_container="meetings"
 partitionKey="/id"
 
 searchName='localink-search'
@@ -79,6 +84,28 @@ az cosmosdb sql database create --account-name $account --resource-group $resour
 echo "Creating $user_container with $partitionKey"
 az cosmosdb sql container create --account-name $account --resource-group $resourceGroup --database-name $database --name $user_container --partition-key-path $partitionKey
 
+# Create the container of the matches, one document per pair of users
+# The composite index lets the matches of a user be listed most recently updated first
+echo "Creating $match_container with $partitionKey"
+matchIndexingPolicy='{"indexingMode": "consistent", "includedPaths": [{"path": "/*"}], "compositeIndexes": [[{"path": "/updated_at", "order": "descending"}, {"path": "/id", "order": "ascending"}]]}'
+az cosmosdb sql container create --account-name $account --resource-group $resourceGroup --database-name $database --name $match_container --partition-key-path $partitionKey --idx "$matchIndexingPolicy"
+
+# Create the container of the reports of abusive users, reviewed by moderators
+echo "Creating $report_container with $partitionKey"
+az cosmosdb sql container create --account-name $account --resource-group $resourceGroup --database-name $database --name $report_container --partition-key-path $partitionKey
+
+# Create the container of the points of interest suggested as meeting points
+echo "Creating $poi_container with $partitionKey"
+az cosmosdb sql container create --account-name $account --resource-group $resourceGroup --database-name $database --name $poi_container --partition-key-path $partitionKey
+
+# Create the container of the meeting points chosen for groups of users
+echo "Creating $meetup_container with $partitionKey"
+az cosmosdb sql container create --account-name $account --resource-group $resourceGroup --database-name $database --name $meetup_container --partition-key-path $partitionKey
+
+# Create the container of the meetings proposed between matched users
+echo "Creating $meeting_container with $partitionKey"
+az cosmosdb sql container create --account-name $account --resource-group $resourceGroup --database-name $database --name $meeting_container --partition-key-path $partitionKey
+
 # Azure Cognitive Search
 echo "Creating search service"
 az search service create --name $searchName --resource-group $resourceGroup --sku Free
@@ -140,6 +167,15 @@ jsonBody=$(cat <<EOF
       "sortable": true,
       "facetable": false,
       "retrievable": true
+    },
+    {
+      "name": "blocked_user_ids",
+      "type": "Collection(Edm.String)",
+      "searchable": false,
+      "filterable": true,
+      "sortable": false,
+      "facetable": false,
+      "retrievable": false
     }
   ],
   "vectorSearch": {
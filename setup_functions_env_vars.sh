@@ -37,6 +37,12 @@ cosmosKey="${cosmosKey%$'\r'}"
 adminKey=$(az search admin-key show -g $resourceGroup --service-name $searchName --query primaryKey --out tsv)
 adminKey="${adminKey%$'\r'}"
 
+# Keep the existing token hash secret if any, as changing it logs every user out
+tokenHashSecret=$(sed -n 's/.*"TOKEN_HASH_SECRET": *"\([0-9a-f]*\)".*/\1/p' local.settings.json 2>/dev/null)
+if [ -z "$tokenHashSecret" ]; then
+        tokenHashSecret=$(openssl rand -hex 32)
+fi
+
 echo "Configuring local settings for local Azure function execution..."
 cp local.settings.template.json local.settings.json
 
@@ -44,6 +50,7 @@ sed -i -e "s/\${{COSMOS_PRIMARY_KEY}}/$cosmosKey/g" local.settings.json
 sed -i -e "s/\${{SEARCH_ADMIN_KEY}}/$adminKey/g" local.settings.json
 sed -i -e "s/\${{OPENAI_API_KEY}}/$openaiKey/g" local.settings.json
 sed -i -e "s/\${{GOOGLE_CLIENT_ID}}/$googleClientId/g" local.settings.json
+sed -i -e "s/\${{TOKEN_HASH_SECRET}}/$tokenHashSecret/g" local.settings.json
 
 echo "Copying local settings to each Azure function source directory..."
 cp local.settings.json Auth/local.settings.json
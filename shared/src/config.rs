@@ -0,0 +1,610 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    fs,
+    net::SocketAddr,
+    num::{NonZeroU32, NonZeroUsize},
+    path::Path,
+    str::FromStr,
+};
+
+use chrono::Duration;
+
+use crate::embeddings::DEFAULT_EMBEDDING_DIMENSIONS;
+
+/// Typed configuration shared by every function, loaded once at startup.
+///
+/// Keys use the same names as the environment variables (and `local.settings.json` values) they come from.
+/// Values are looked up, from lowest to highest priority, in:
+/// - the `Values` of an Azure `local.settings.json` file (path in `LOCALINK_SETTINGS`, `local.settings.json` by default),
+/// - a TOML file with the same flat keys (path in `LOCALINK_CONFIG`, `localink.toml` by default),
+/// - the process environment.
+///
+/// Missing default files are skipped, while files explicitly pointed to must exist.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Port an Azure Functions custom handler listens on (`FUNCTIONS_CUSTOMHANDLER_PORT`).
+    pub custom_handler_port: u16,
+    /// Address the single server binary listens on (`LOCALINK_ADDRESS`).
+    pub server_address: SocketAddr,
+    /// Client ID of the Google OAuth app (`GOOGLE_CLIENT_ID`). Google logins are refused when missing.
+    pub google_client_id: Option<String>,
+    /// Service ID used for Sign in with Apple (`APPLE_CLIENT_ID`). Apple logins are refused when missing.
+    pub apple_client_id: Option<String>,
+    /// Generic OpenID Connect provider, enabled by setting `OIDC_ISSUER`.
+    pub oidc: Option<OidcConfig>,
+    /// Lifetime of access tokens (`ACCESS_TOKEN_TTL_MINUTES`, 60 by default).
+    pub access_token_ttl: Duration,
+    /// Lifetime of refresh tokens, i.e. how long a device stays logged in without being used
+    /// (`REFRESH_TOKEN_TTL_DAYS`, 30 by default).
+    pub refresh_token_ttl: Duration,
+    /// Secret key tokens are hashed with before being stored (`TOKEN_HASH_SECRET`).
+    /// Changing it invalidates every session.
+    pub token_hash_secret: String,
+    /// How long a search result can be turned into a match request
+    /// (`RECOMMENDATION_TICKET_TTL_MINUTES`, 1440 by default).
+    pub recommendation_ticket_ttl: Duration,
+    /// GeoJSON or CSV file of points of interest (`POI_FILE`), see [`crate::pois::import`]. Imported by
+    /// `localink-server import-pois`, and when the server starts with the in-memory store.
+    pub poi_file: Option<String>,
+    /// Key required by the management API of the points of interest (`ADMIN_API_KEY`).
+    /// The management API is disabled when missing.
+    pub admin_api_key: Option<String>,
+    pub user_store: UserStoreConfig,
+    pub vector_index: VectorIndexConfig,
+    pub embeddings: EmbeddingConfig,
+}
+
+#[derive(Clone, Debug)]
+pub struct OidcConfig {
+    /// Expected `iss` claim of the ID tokens (`OIDC_ISSUER`).
+    pub issuer: String,
+    /// Expected `aud` claim of the ID tokens (`OIDC_CLIENT_ID`).
+    pub client_id: String,
+    pub jwks: JwksSource,
+}
+
+/// Where the keys signing the ID tokens of an OpenID Connect provider are read from.
+#[derive(Clone, Debug)]
+pub enum JwksSource {
+    /// Fetched from the provider (`OIDC_JWKS_URL`), and fetched again when an unknown key shows up.
+    Url(String),
+    /// Read from a local file (`OIDC_JWKS_PATH`), e.g. for tests without network access, and read
+    /// again like remote keys.
+    File(String),
+}
+
+/// Backend selected through `USER_STORE`.
+#[derive(Clone, Debug)]
+pub enum UserStoreConfig {
+    Cosmos(CosmosConfig),
+    Sqlite { path: String },
+    Memory,
+}
+
+#[derive(Clone, Debug)]
+pub struct CosmosConfig {
+    pub primary_key: String,
+    pub account: String,
+    pub database: String,
+    pub users_collection: String,
+    pub matches_collection: String,
+    pub reports_collection: String,
+    pub pois_collection: String,
+    pub meetups_collection: String,
+    pub meetings_collection: String,
+}
+
+/// Backend selected through `VECTOR_INDEX`.
+#[derive(Clone, Debug)]
+pub enum VectorIndexConfig {
+    Cognitive {
+        endpoint: String,
+        index_name: String,
+        admin_key: String,
+    },
+    Local,
+}
+
+/// Provider selected through `EMBEDDING_PROVIDER`.
+#[derive(Clone, Debug)]
+pub enum EmbeddingConfig {
+    OpenAI {
+        api_key: String,
+        base_url: String,
+        model: String,
+        dimensions: usize,
+    },
+    AzureOpenAI {
+        api_key: String,
+        endpoint: String,
+        deployment: String,
+        api_version: String,
+        model: String,
+        dimensions: usize,
+    },
+    Fixture {
+        path: String,
+    },
+    Hashing {
+        dimensions: usize,
+    },
+}
+
+#[derive(Debug)]
+pub enum ConfigProblem {
+    Missing(String),
+    Invalid {
+        key: String,
+        value: String,
+        reason: String,
+    },
+    UnreadableFile {
+        path: String,
+        reason: String,
+    },
+}
+
+impl Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigProblem::Missing(key) => write!(f, "{} is missing", key),
+            ConfigProblem::Invalid { key, value, reason } => {
+                write!(f, "{} has an invalid value {:?}: {}", key, value, reason)
+            }
+            ConfigProblem::UnreadableFile { path, reason } => {
+                write!(f, "could not read {}: {}", path, reason)
+            }
+        }
+    }
+}
+
+/// Every problem found while loading the configuration, so they can all be fixed at once.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub problems: Vec<ConfigProblem>,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Invalid configuration:")?;
+        for problem in &self.problems {
+            writeln!(f, "- {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Raw key-value settings merged from every source, collecting problems while they're read.
+struct Settings {
+    values: HashMap<String, String>,
+    problems: Vec<ConfigProblem>,
+}
+
+impl Settings {
+    /// Reads a value, empty ones counting as missing. Placeholders of the settings template left
+    /// as they are are refused, rather than used as secrets anyone can read in the repository.
+    fn optional(&mut self, key: &str) -> Option<String> {
+        let value = self
+            .values
+            .get(key)
+            .filter(|value| !value.is_empty())
+            .cloned()?;
+        if value.starts_with("${{") && value.ends_with("}}") {
+            self.problems.push(ConfigProblem::Invalid {
+                key: key.to_owned(),
+                value,
+                reason: "placeholder of the settings template that was never replaced".to_owned(),
+            });
+            return None;
+        }
+        Some(value)
+    }
+
+    fn required(&mut self, key: &str) -> String {
+        let present = self.values.get(key).is_some_and(|value| !value.is_empty());
+        match self.optional(key) {
+            Some(value) => value,
+            None => {
+                // Placeholders are already reported as invalid
+                if !present {
+                    self.problems.push(ConfigProblem::Missing(key.to_owned()));
+                }
+                String::new()
+            }
+        }
+    }
+
+    fn or_default(&mut self, key: &str, default: &str) -> String {
+        self.optional(key).unwrap_or_else(|| default.to_owned())
+    }
+
+    fn parsed<T>(&mut self, key: &str, default: T) -> T
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let Some(value) = self.optional(key) else {
+            return default;
+        };
+        value.parse().unwrap_or_else(|err: T::Err| {
+            self.problems.push(ConfigProblem::Invalid {
+                key: key.to_owned(),
+                reason: err.to_string(),
+                value,
+            });
+            default
+        })
+    }
+
+    /// Reads a value that must be one of `allowed`, the first one being the default.
+    fn choice(&mut self, key: &str, allowed: &[&str]) -> String {
+        let value = self.or_default(key, allowed[0]);
+        if !allowed.contains(&value.as_str()) {
+            self.problems.push(ConfigProblem::Invalid {
+                key: key.to_owned(),
+                value: value.clone(),
+                reason: format!("expected one of {}", allowed.join(", ")),
+            });
+        }
+        value
+    }
+
+    /// Merges the values of a file into the settings, overriding the ones already present.
+    /// Files that don't exist are skipped unless `explicit` is set.
+    fn merge_file(
+        &mut self,
+        path: &str,
+        explicit: bool,
+        parse: fn(&str) -> Result<HashMap<String, String>, String>,
+    ) {
+        if !explicit && !Path::new(path).exists() {
+            return;
+        }
+        match fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|content| parse(&content))
+        {
+            Ok(values) => self.values.extend(values),
+            Err(reason) => self.problems.push(ConfigProblem::UnreadableFile {
+                path: path.to_owned(),
+                reason,
+            }),
+        }
+    }
+}
+
+/// Vectors can't be empty, so zero dimensions are refused along with anything that isn't a number.
+fn embedding_dimensions(settings: &mut Settings) -> usize {
+    let default = NonZeroUsize::new(DEFAULT_EMBEDDING_DIMENSIONS).expect("the default is not zero");
+    settings.parsed("EMBEDDING_DIMENSIONS", default).get()
+}
+
+/// Lifetimes of zero would make what they apply to expire as soon as it's issued, so they're refused.
+fn lifetime(settings: &mut Settings, key: &str, default: u32) -> i64 {
+    let default = NonZeroU32::new(default).expect("the defaults are not zero");
+    settings.parsed(key, default).get().into()
+}
+
+fn value_to_string(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Reads the `Values` object of an Azure Functions `local.settings.json` file.
+fn parse_local_settings(content: &str) -> Result<HashMap<String, String>, String> {
+    let settings: serde_json::Value = serde_json::from_str(content).map_err(|err| err.to_string())?;
+    let values = settings
+        .get("Values")
+        .and_then(|values| values.as_object())
+        .ok_or("missing Values object")?;
+    Ok(values
+        .iter()
+        .map(|(key, value)| (key.clone(), value_to_string(value)))
+        .collect())
+}
+
+fn parse_toml(content: &str) -> Result<HashMap<String, String>, String> {
+    let table: toml::Table = content.parse().map_err(|err: toml::de::Error| err.to_string())?;
+    Ok(table
+        .into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                toml::Value::String(value) => value,
+                other => other.to_string(),
+            };
+            (key, value)
+        })
+        .collect())
+}
+
+impl Config {
+    /// Loads and validates the configuration, reporting every missing or invalid key at once.
+    pub fn load() -> Result<Config, ConfigError> {
+        Config::load_from(std::env::vars().collect())
+    }
+
+    /// Loads the configuration as [`Config::load`] does, with the given environment variables.
+    fn load_from(environment: HashMap<String, String>) -> Result<Config, ConfigError> {
+        let mut settings = Settings {
+            values: HashMap::new(),
+            problems: Vec::new(),
+        };
+
+        let local_settings_path = environment.get("LOCALINK_SETTINGS").cloned();
+        settings.merge_file(
+            local_settings_path.as_deref().unwrap_or("local.settings.json"),
+            local_settings_path.is_some(),
+            parse_local_settings,
+        );
+        let config_path = environment.get("LOCALINK_CONFIG").cloned();
+        settings.merge_file(
+            config_path.as_deref().unwrap_or("localink.toml"),
+            config_path.is_some(),
+            parse_toml,
+        );
+        settings.values.extend(environment);
+
+        let config = Config::from_settings(&mut settings);
+        if settings.problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError {
+                problems: settings.problems,
+            })
+        }
+    }
+
+    /// Loads the configuration, exiting with a report of every problem found if it's invalid.
+    pub fn load_or_exit() -> Config {
+        Config::load().unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        })
+    }
+
+    fn from_settings(settings: &mut Settings) -> Config {
+        let custom_handler_port = settings.parsed("FUNCTIONS_CUSTOMHANDLER_PORT", 3000);
+        let server_address = settings.parsed("LOCALINK_ADDRESS", ([127, 0, 0, 1], 3000).into());
+        let google_client_id = settings.optional("GOOGLE_CLIENT_ID");
+        let apple_client_id = settings.optional("APPLE_CLIENT_ID");
+        let oidc = settings.optional("OIDC_ISSUER").map(|issuer| OidcConfig {
+            issuer,
+            client_id: settings.required("OIDC_CLIENT_ID"),
+            jwks: match settings.optional("OIDC_JWKS_PATH") {
+                Some(path) => JwksSource::File(path),
+                None => JwksSource::Url(settings.required("OIDC_JWKS_URL")),
+            },
+        });
+        let access_token_ttl = Duration::minutes(lifetime(settings, "ACCESS_TOKEN_TTL_MINUTES", 60));
+        let refresh_token_ttl = Duration::days(lifetime(settings, "REFRESH_TOKEN_TTL_DAYS", 30));
+        let token_hash_secret = settings.required("TOKEN_HASH_SECRET");
+        let recommendation_ticket_ttl =
+            Duration::minutes(lifetime(settings, "RECOMMENDATION_TICKET_TTL_MINUTES", 1440));
+        let poi_file = settings.optional("POI_FILE");
+        let admin_api_key = settings.optional("ADMIN_API_KEY");
+
+        let user_store = match settings.choice("USER_STORE", &["cosmos", "sqlite", "memory"]).as_str()
+        {
+            "sqlite" => UserStoreConfig::Sqlite {
+                path: settings.or_default("SQLITE_PATH", "localink.db"),
+            },
+            "memory" => UserStoreConfig::Memory,
+            _ => UserStoreConfig::Cosmos(CosmosConfig {
+                primary_key: settings.required("COSMOS_PRIMARY_KEY"),
+                account: settings.required("COSMOS_ACCOUNT"),
+                database: settings.required("COSMOS_DB"),
+                users_collection: settings.required("USERS_TABLE"),
+                matches_collection: settings.or_default("MATCHES_TABLE", "matches"),
+                reports_collection: settings.or_default("REPORTS_TABLE", "reports"),
+                pois_collection: settings.or_default("POIS_TABLE", "pois"),
+                meetups_collection: settings.or_default("MEETUPS_TABLE", "meetups"),
+                meetings_collection: settings.or_default("MEETINGS_TABLE", "meetings"),
+            }),
+        };
+
+        let vector_index = match settings.choice("VECTOR_INDEX", &["cognitive", "local"]).as_str() {
+            "local" => VectorIndexConfig::Local,
+            _ => VectorIndexConfig::Cognitive {
+                endpoint: settings.required("SEARCH_ENDPOINT"),
+                index_name: settings.required("SEARCH_INDEX_NAME"),
+                admin_key: settings.required("SEARCH_ADMIN_KEY"),
+            },
+        };
+
+        let embeddings = match settings
+            .choice(
+                "EMBEDDING_PROVIDER",
+                &["openai", "azure_openai", "fixture", "hashing"],
+            )
+            .as_str()
+        {
+            "azure_openai" => EmbeddingConfig::AzureOpenAI {
+                api_key: settings.required("AZURE_OPENAI_API_KEY"),
+                endpoint: settings.required("AZURE_OPENAI_ENDPOINT"),
+                deployment: settings.required("AZURE_OPENAI_DEPLOYMENT"),
+                api_version: settings.or_default("AZURE_OPENAI_API_VERSION", "2023-05-15"),
+                model: settings.required("OPENAI_MODEL"),
+                dimensions: embedding_dimensions(settings),
+            },
+            "fixture" => EmbeddingConfig::Fixture {
+                path: settings.or_default("EMBEDDING_FIXTURE_PATH", "example_openai_response.json"),
+            },
+            "hashing" => EmbeddingConfig::Hashing {
+                dimensions: embedding_dimensions(settings),
+            },
+            _ => EmbeddingConfig::OpenAI {
+                api_key: settings.required("OPENAI_API_KEY"),
+                base_url: settings.required("OPENAI_BASE_URL"),
+                model: settings.required("OPENAI_MODEL"),
+                dimensions: embedding_dimensions(settings),
+            },
+        };
+
+        Config {
+            custom_handler_port,
+            server_address,
+            google_client_id,
+            apple_client_id,
+            oidc,
+            access_token_ttl,
+            refresh_token_ttl,
+            token_hash_secret,
+            recommendation_ticket_ttl,
+            poi_file,
+            admin_api_key,
+            user_store,
+            vector_index,
+            embeddings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Writes a settings file only this test reads.
+    fn settings_file(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("localink-config-{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    /// Environment of a configuration with every backend local, and the settings files given.
+    fn environment(local_settings: &Path, toml: &Path, values: &[(&str, &str)]) -> HashMap<String, String> {
+        [
+            ("LOCALINK_SETTINGS", local_settings.to_str().unwrap()),
+            ("LOCALINK_CONFIG", toml.to_str().unwrap()),
+            ("USER_STORE", "memory"),
+            ("VECTOR_INDEX", "local"),
+            ("EMBEDDING_PROVIDER", "hashing"),
+            ("TOKEN_HASH_SECRET", "secret"),
+        ]
+        .iter()
+        .chain(values)
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+    }
+
+    fn empty_files(name: &str) -> (PathBuf, PathBuf) {
+        (
+            settings_file(&format!("{}.json", name), r#"{"Values": {}}"#),
+            settings_file(&format!("{}.toml", name), ""),
+        )
+    }
+
+    fn problems(values: &[(&str, &str)], name: &str) -> Vec<String> {
+        let (local_settings, toml) = empty_files(name);
+        let err = Config::load_from(environment(&local_settings, &toml, values)).unwrap_err();
+        err.problems.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn local_configuration_loads_with_the_defaults() {
+        let (local_settings, toml) = empty_files("defaults");
+        let config = Config::load_from(environment(&local_settings, &toml, &[])).unwrap();
+        assert_eq!(config.access_token_ttl, Duration::minutes(60));
+        assert_eq!(config.refresh_token_ttl, Duration::days(30));
+        assert_eq!(config.recommendation_ticket_ttl, Duration::minutes(1440));
+        assert!(matches!(config.user_store, UserStoreConfig::Memory));
+        assert!(matches!(
+            config.embeddings,
+            EmbeddingConfig::Hashing { dimensions: DEFAULT_EMBEDDING_DIMENSIONS }
+        ));
+    }
+
+    #[test]
+    fn environment_overrides_toml_which_overrides_local_settings() {
+        let local_settings = settings_file(
+            "precedence.json",
+            r#"{"Values": {"ACCESS_TOKEN_TTL_MINUTES": "10", "REFRESH_TOKEN_TTL_DAYS": 10, "RECOMMENDATION_TICKET_TTL_MINUTES": "10"}}"#,
+        );
+        let toml = settings_file(
+            "precedence.toml",
+            "REFRESH_TOKEN_TTL_DAYS = 20\nRECOMMENDATION_TICKET_TTL_MINUTES = \"20\"\n",
+        );
+        let environment = environment(&local_settings, &toml, &[("RECOMMENDATION_TICKET_TTL_MINUTES", "30")]);
+
+        let config = Config::load_from(environment).unwrap();
+        assert_eq!(config.access_token_ttl, Duration::minutes(10));
+        assert_eq!(config.refresh_token_ttl, Duration::days(20));
+        assert_eq!(config.recommendation_ticket_ttl, Duration::minutes(30));
+    }
+
+    #[test]
+    fn every_missing_key_is_reported() {
+        let problems = problems(&[("USER_STORE", "cosmos"), ("TOKEN_HASH_SECRET", "")], "missing");
+        assert_eq!(
+            problems,
+            [
+                "TOKEN_HASH_SECRET is missing",
+                "COSMOS_PRIMARY_KEY is missing",
+                "COSMOS_ACCOUNT is missing",
+                "COSMOS_DB is missing",
+                "USERS_TABLE is missing",
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_reported() {
+        let problems = problems(
+            &[
+                ("FUNCTIONS_CUSTOMHANDLER_PORT", "http"),
+                ("LOCALINK_ADDRESS", "localhost"),
+                ("ACCESS_TOKEN_TTL_MINUTES", "0"),
+                ("REFRESH_TOKEN_TTL_DAYS", "0"),
+                ("RECOMMENDATION_TICKET_TTL_MINUTES", "-5"),
+                ("EMBEDDING_DIMENSIONS", "0"),
+            ],
+            "invalid",
+        );
+        let keys: Vec<&str> = problems
+            .iter()
+            .map(|problem| problem.split_once(' ').unwrap().0)
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                "FUNCTIONS_CUSTOMHANDLER_PORT",
+                "LOCALINK_ADDRESS",
+                "ACCESS_TOKEN_TTL_MINUTES",
+                "REFRESH_TOKEN_TTL_DAYS",
+                "RECOMMENDATION_TICKET_TTL_MINUTES",
+                "EMBEDDING_DIMENSIONS",
+            ]
+        );
+        assert!(problems.iter().all(|problem| problem.contains("has an invalid value")), "{:?}", problems);
+    }
+
+    #[test]
+    fn placeholders_are_refused_but_not_reported_as_missing() {
+        let problems = problems(&[("TOKEN_HASH_SECRET", "${{TOKEN_HASH_SECRET}}")], "placeholder");
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert!(problems[0].starts_with("TOKEN_HASH_SECRET has an invalid value"), "{:?}", problems);
+        assert!(problems[0].contains("placeholder"), "{:?}", problems);
+    }
+
+    #[test]
+    fn files_pointed_to_must_exist() {
+        let (_, toml) = empty_files("unreadable");
+        let missing = std::env::temp_dir().join("localink-config-that-does-not-exist.json");
+        let err = Config::load_from(environment(&missing, &toml, &[])).unwrap_err();
+        assert!(
+            matches!(&err.problems[..], [ConfigProblem::UnreadableFile { path, .. }] if Path::new(path) == missing),
+            "{:?}",
+            err.problems
+        );
+
+        let malformed = settings_file("malformed.json", r#"{"NotValues": {}}"#);
+        let err = Config::load_from(environment(&malformed, &toml, &[])).unwrap_err();
+        assert!(matches!(&err.problems[..], [ConfigProblem::UnreadableFile { .. }]), "{:?}", err.problems);
+    }
+}
@@ -0,0 +1,50 @@
+use std::fs;
+
+use async_trait::async_trait;
+
+use crate::AppError;
+
+use super::{openai::OpenAIResponse, EmbeddingProvider};
+
+/// Returns the embeddings stored in a saved OpenAI response (such as `example_openai_response.json`)
+/// for any input. Useful for debugging the rest of the flow without calling the API.
+pub struct FixtureEmbeddingProvider {
+    model: String,
+    embeddings: Vec<f64>,
+}
+
+impl FixtureEmbeddingProvider {
+    pub fn load(path: &str) -> Result<Self, AppError> {
+        let file = fs::File::open(path).map_err(|err| {
+            println!("Could not open {}: {:?}", path, err);
+            AppError::GenericError
+        })?;
+        let openai_response: OpenAIResponse = serde_json::from_reader(file)?;
+        let embeddings = openai_response
+            .data
+            .into_iter()
+            .next()
+            .ok_or(AppError::GenericError)?
+            .embedding;
+
+        Ok(FixtureEmbeddingProvider {
+            model: openai_response.model,
+            embeddings,
+        })
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for FixtureEmbeddingProvider {
+    async fn embed(&self, _input: &str) -> Result<Vec<f64>, AppError> {
+        Ok(self.embeddings.clone())
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn dimensions(&self) -> usize {
+        self.embeddings.len()
+    }
+}
@@ -0,0 +1,63 @@
+use async_trait::async_trait;
+
+use crate::AppError;
+
+use super::EmbeddingProvider;
+
+/// Offline provider based on the hashing trick: every lowercased word (and pair of adjacent words) is
+/// hashed into one of the vector's dimensions. The same text always produces the same vector, and texts
+/// sharing words end up close to each other, which is enough to exercise the search without a model.
+pub struct HashingEmbeddingProvider {
+    model: String,
+    dimensions: usize,
+}
+
+impl HashingEmbeddingProvider {
+    pub fn new(dimensions: usize) -> Self {
+        HashingEmbeddingProvider {
+            model: format!("hashing-{}", dimensions),
+            dimensions,
+        }
+    }
+}
+
+/// 64-bit FNV-1a, used instead of the std hasher since its output must never change between builds.
+fn fnv1a(input: &str) -> u64 {
+    input.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[async_trait]
+impl EmbeddingProvider for HashingEmbeddingProvider {
+    async fn embed(&self, input: &str) -> Result<Vec<f64>, AppError> {
+        let words: Vec<String> = input
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_lowercase())
+            .collect();
+        let bigrams = words.windows(2).map(|pair| pair.join(" "));
+
+        let mut embeddings = vec![0.; self.dimensions];
+        for feature in words.iter().cloned().chain(bigrams) {
+            let hash = fnv1a(&feature);
+            // The top bit picks the sign, so that collisions tend to cancel out instead of piling up
+            let sign = if hash >> 63 == 0 { 1. } else { -1. };
+            embeddings[(hash % self.dimensions as u64) as usize] += sign;
+        }
+
+        let norm = embeddings.iter().map(|value| value * value).sum::<f64>().sqrt();
+        if norm > 0. {
+            embeddings.iter_mut().for_each(|value| *value /= norm);
+        }
+        Ok(embeddings)
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{config::EmbeddingConfig, AppError};
+
+mod fixture;
+mod hashing;
+mod openai;
+
+pub use fixture::FixtureEmbeddingProvider;
+pub use hashing::HashingEmbeddingProvider;
+pub use openai::{AzureOpenAIEmbeddingProvider, OpenAIEmbeddingProvider};
+
+/// Dimensions of the vectors produced by `text-embedding-ada-002`, which the search index is configured for.
+pub const DEFAULT_EMBEDDING_DIMENSIONS: usize = 1536;
+
+/// Converts text into the vectors used for the similarity search.
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    async fn embed(&self, input: &str) -> Result<Vec<f64>, AppError>;
+
+    /// Name of the model producing the embeddings.
+    fn model(&self) -> &str;
+
+    /// Length of the produced vectors.
+    fn dimensions(&self) -> usize;
+
+    /// Checks that a vector could have been produced by this provider before it gets stored.
+    fn validate(&self, embeddings: &[f64]) -> Result<(), AppError> {
+        if embeddings.len() != self.dimensions() {
+            println!(
+                "Expected {} dimensions from {}, got {}",
+                self.dimensions(),
+                self.model(),
+                embeddings.len()
+            );
+            return Err(AppError::GenericError);
+        }
+        if embeddings.iter().any(|value| !value.is_finite()) {
+            println!("Embeddings from {} contain non finite values", self.model());
+            return Err(AppError::GenericError);
+        }
+        Ok(())
+    }
+}
+
+/// Builds the configured embedding provider.
+pub fn get_embedding_provider(embedding_config: &EmbeddingConfig) -> Arc<dyn EmbeddingProvider> {
+    match embedding_config.clone() {
+        EmbeddingConfig::OpenAI {
+            api_key,
+            base_url,
+            model,
+            dimensions,
+        } => Arc::new(OpenAIEmbeddingProvider::new(api_key, base_url, model, dimensions)),
+        EmbeddingConfig::AzureOpenAI {
+            api_key,
+            endpoint,
+            deployment,
+            api_version,
+            model,
+            dimensions,
+        } => Arc::new(AzureOpenAIEmbeddingProvider::new(
+            api_key,
+            endpoint,
+            deployment,
+            api_version,
+            model,
+            dimensions,
+        )),
+        EmbeddingConfig::Fixture { path } => Arc::new(
+            FixtureEmbeddingProvider::load(&path)
+                .expect("Could not load the embeddings fixture file"),
+        ),
+        EmbeddingConfig::Hashing { dimensions } => {
+            Arc::new(HashingEmbeddingProvider::new(dimensions))
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use hyper::StatusCode;
+use serde::Deserialize;
+
+use crate::AppError;
+
+use super::EmbeddingProvider;
+
+/// Response of the embeddings endpoint, shared by OpenAI and Azure OpenAI.
+/// Reference: https://platform.openai.com/docs/api-reference/embeddings/object
+#[derive(Deserialize, Debug)]
+pub(super) struct OpenAIResponse {
+    pub data: Vec<OpenAIEmbeddings>,
+    pub model: String,
+    pub usage: OpenAIUsage,
+}
+
+#[derive(Deserialize, Debug)]
+pub(super) struct OpenAIEmbeddings {
+    pub embedding: Vec<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(super) struct OpenAIUsage {
+    pub total_tokens: u32,
+}
+
+impl OpenAIResponse {
+    fn into_embeddings(self) -> Result<Vec<f64>, AppError> {
+        println!(
+            "Embeddings generated by {} using {} tokens",
+            self.model, self.usage.total_tokens
+        );
+        self.data
+            .into_iter()
+            .next()
+            .map(|data| data.embedding)
+            .ok_or(AppError::GenericError)
+    }
+}
+
+async fn parse_response(response: reqwest::Response) -> Result<Vec<f64>, AppError> {
+    match response.status() {
+        StatusCode::OK => response.json::<OpenAIResponse>().await?.into_embeddings(),
+        status => {
+            println!("Embeddings request failed with status {}", status);
+            Err(AppError::GenericError)
+        }
+    }
+}
+
+/// Embeddings generated through the OpenAI API (e.g. with the Ada model).
+pub struct OpenAIEmbeddingProvider {
+    api_key: String,
+    base_url: String,
+    model: String,
+    dimensions: usize,
+}
+
+impl OpenAIEmbeddingProvider {
+    pub fn new(api_key: String, base_url: String, model: String, dimensions: usize) -> Self {
+        OpenAIEmbeddingProvider {
+            api_key,
+            base_url,
+            model,
+            dimensions,
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAIEmbeddingProvider {
+    async fn embed(&self, input: &str) -> Result<Vec<f64>, AppError> {
+        let mut map = HashMap::new();
+        map.insert("input", input);
+        map.insert("model", &self.model);
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(&self.base_url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .json(&map)
+            .send()
+            .await?;
+
+        parse_response(response).await
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+}
+
+/// Embeddings generated through a model deployed on Azure OpenAI.
+/// The model name is only informative, as the deployment decides which model is used.
+pub struct AzureOpenAIEmbeddingProvider {
+    api_key: String,
+    endpoint: String,
+    deployment: String,
+    api_version: String,
+    model: String,
+    dimensions: usize,
+}
+
+impl AzureOpenAIEmbeddingProvider {
+    pub fn new(
+        api_key: String,
+        endpoint: String,
+        deployment: String,
+        api_version: String,
+        model: String,
+        dimensions: usize,
+    ) -> Self {
+        AzureOpenAIEmbeddingProvider {
+            api_key,
+            endpoint,
+            deployment,
+            api_version,
+            model,
+            dimensions,
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for AzureOpenAIEmbeddingProvider {
+    async fn embed(&self, input: &str) -> Result<Vec<f64>, AppError> {
+        let mut map = HashMap::new();
+        map.insert("input", input);
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!(
+                "{}/openai/deployments/{}/embeddings",
+                self.endpoint.trim_end_matches('/'),
+                self.deployment
+            ))
+            .query(&[("api-version", &self.api_version)])
+            .header("api-key", &self.api_key)
+            .json(&map)
+            .send()
+            .await?;
+
+        parse_response(response).await
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+}
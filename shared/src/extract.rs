@@ -0,0 +1,86 @@
+//! Request extractors shared by the functions.
+
+use axum::{
+    async_trait,
+    body::HttpBody,
+    extract::{rejection::JsonRejection, FromRequest},
+    http::{header, Request},
+    BoxError, Json,
+};
+use serde::de::DeserializeOwned;
+
+/// JSON body of an endpoint whose parameters are all optional, so that the body can be left out.
+/// Unlike `Option<Json<T>>`, which drops any error, a body that is sent but malformed is refused
+/// (400 for invalid JSON, 422 for the wrong fields, 415 for another content type).
+pub struct OptionalJson<T>(pub Option<T>);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for OptionalJson<T>
+where
+    T: DeserializeOwned,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    type Rejection = JsonRejection;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let headers = req.headers();
+        let empty = headers.get(header::CONTENT_LENGTH).is_some_and(|length| length == "0");
+        if empty || !headers.contains_key(header::CONTENT_TYPE) {
+            return Ok(OptionalJson(None));
+        }
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        Ok(OptionalJson(Some(value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::StatusCode, response::IntoResponse};
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Options {
+        all: bool,
+    }
+
+    async fn extract(content_type: Option<&str>, body: &'static str) -> Result<Option<Options>, StatusCode> {
+        let mut request = Request::builder().method("POST").uri("/");
+        if let Some(content_type) = content_type {
+            request = request.header(header::CONTENT_TYPE, content_type);
+        }
+        let request = request
+            .header(header::CONTENT_LENGTH, body.len())
+            .body(Body::from(body))
+            .unwrap();
+        OptionalJson::<Options>::from_request(request, &())
+            .await
+            .map(|OptionalJson(options)| options)
+            .map_err(|rejection| rejection.into_response().status())
+    }
+
+    #[tokio::test]
+    async fn missing_bodies_are_none() {
+        assert_eq!(extract(None, "").await, Ok(None));
+        assert_eq!(extract(Some("application/json"), "").await, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn valid_bodies_are_read() {
+        let options = extract(Some("application/json"), r#"{"all": true}"#).await;
+        assert_eq!(options, Ok(Some(Options { all: true })));
+    }
+
+    #[tokio::test]
+    async fn malformed_bodies_are_refused() {
+        assert_eq!(extract(Some("application/json"), "{").await, Err(StatusCode::BAD_REQUEST));
+        let wrong_type = extract(Some("application/json"), r#"{"all": "yes"}"#).await;
+        assert_eq!(wrong_type, Err(StatusCode::UNPROCESSABLE_ENTITY));
+        let not_json = extract(Some("text/plain"), "all").await;
+        assert_eq!(not_json, Err(StatusCode::UNSUPPORTED_MEDIA_TYPE));
+    }
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+/// Coordinates outside of their valid range, or not finite.
+#[derive(Debug)]
+pub struct InvalidCoordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// A location on Earth, serialized as a GeoJSON point: `{"type": "Point", "coordinates": [longitude, latitude]}`.
+/// This is also the layout Cosmos DB and Cognitive Search expect, so coordinates are only accessed
+/// by name to avoid swapping them.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(try_from = "GeoJsonPoint", into = "GeoJsonPoint")]
+pub struct GeoPoint {
+    latitude: f64,
+    longitude: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GeoJsonPoint {
+    #[serde(rename = "type")]
+    r#type: String,
+    coordinates: [f64; 2],
+}
+
+impl GeoPoint {
+    /// Fails unless the latitude is within [-90, 90] and the longitude within [-180, 180].
+    pub fn new(latitude: f64, longitude: f64) -> Result<Self, InvalidCoordinates> {
+        if !(-90. ..=90.).contains(&latitude) || !(-180. ..=180.).contains(&longitude) {
+            return Err(InvalidCoordinates {
+                latitude,
+                longitude,
+            });
+        }
+        Ok(GeoPoint {
+            latitude,
+            longitude,
+        })
+    }
+
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+}
+
+impl TryFrom<GeoJsonPoint> for GeoPoint {
+    type Error = String;
+
+    fn try_from(point: GeoJsonPoint) -> Result<Self, Self::Error> {
+        if point.r#type != "Point" {
+            return Err(format!("expected a Point, found {}", point.r#type));
+        }
+        let [longitude, latitude] = point.coordinates;
+        GeoPoint::new(latitude, longitude)
+            .map_err(|err| format!("invalid coordinates {:?}", err))
+    }
+}
+
+impl From<GeoPoint> for GeoJsonPoint {
+    fn from(point: GeoPoint) -> Self {
+        GeoJsonPoint {
+            r#type: "Point".to_owned(),
+            coordinates: [point.longitude, point.latitude],
+        }
+    }
+}
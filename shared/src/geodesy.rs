@@ -0,0 +1,250 @@
+//! Computations on the surface of the Earth, modelled as a sphere.
+//! Good to a few meters over the distances users meet at, far more than needed here.
+
+use crate::GeoPoint;
+
+/// Mean radius of the Earth.
+pub const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+/// Great-circle distance between two points, in km (haversine formula).
+pub fn haversine_km(a: &GeoPoint, b: &GeoPoint) -> f64 {
+    let (lat_a, lon_a) = (a.latitude().to_radians(), a.longitude().to_radians());
+    let (lat_b, lon_b) = (b.latitude().to_radians(), b.longitude().to_radians());
+    let h = ((lat_b - lat_a) / 2.).sin().powi(2)
+        + lat_a.cos() * lat_b.cos() * ((lon_b - lon_a) / 2.).sin().powi(2);
+    // Rounding can push h slightly above 1 for antipodal points
+    2. * EARTH_RADIUS_KM * h.sqrt().min(1.).asin()
+}
+
+/// Point halfway along the great circle between two points. Unlike averaging the coordinates, this
+/// works across the antimeridian and near the poles. Antipodal points have no single midpoint, any
+/// point of the great circle halfway between them can be returned.
+pub fn midpoint(a: &GeoPoint, b: &GeoPoint) -> GeoPoint {
+    let (lat_a, lon_a) = (a.latitude().to_radians(), a.longitude().to_radians());
+    let (lat_b, lon_b) = (b.latitude().to_radians(), b.longitude().to_radians());
+    let delta_lon = lon_b - lon_a;
+
+    let bx = lat_b.cos() * delta_lon.cos();
+    let by = lat_b.cos() * delta_lon.sin();
+    let latitude = (lat_a.sin() + lat_b.sin()).atan2(((lat_a.cos() + bx).powi(2) + by.powi(2)).sqrt());
+    let longitude = lon_a + by.atan2(lat_a.cos() + bx);
+
+    GeoPoint::new(
+        latitude.to_degrees().clamp(-90., 90.),
+        normalize_longitude(longitude.to_degrees()),
+    )
+    .expect("coordinates are normalized")
+}
+
+/// Center of a set of points, i.e. the normalized average of their positions in 3D. For two points
+/// this is their [`midpoint`]. Points spread evenly around the globe have no center, the first point
+/// is returned then. Panics on an empty slice.
+pub fn centroid(points: &[GeoPoint]) -> GeoPoint {
+    let (mut x, mut y, mut z) = (0., 0., 0.);
+    for point in points {
+        let (latitude, longitude) = (point.latitude().to_radians(), point.longitude().to_radians());
+        x += latitude.cos() * longitude.cos();
+        y += latitude.cos() * longitude.sin();
+        z += latitude.sin();
+    }
+    if (x * x + y * y + z * z).sqrt() < 1e-9 {
+        return points[0];
+    }
+
+    GeoPoint::new(
+        z.atan2((x * x + y * y).sqrt()).to_degrees().clamp(-90., 90.),
+        normalize_longitude(y.atan2(x).to_degrees()),
+    )
+    .expect("coordinates are normalized")
+}
+
+/// Brings a longitude in degrees back within [-180, 180].
+fn normalize_longitude(longitude: f64) -> f64 {
+    let normalized = (longitude + 180.).rem_euclid(360.) - 180.;
+    // rem_euclid maps 180 to -180, keep the sign of the input for that meridian
+    if normalized == -180. && longitude > 0. {
+        180.
+    } else {
+        normalized
+    }
+}
+
+/// Smallest latitude/longitude rectangle containing every point within a distance of a center.
+/// Checking the box first is much cheaper than computing each distance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min_latitude: f64,
+    pub max_latitude: f64,
+    /// Greater than `max_longitude` when the box crosses the antimeridian.
+    pub min_longitude: f64,
+    pub max_longitude: f64,
+}
+
+impl BoundingBox {
+    /// Box around all the points within `radius_km` of `center`.
+    pub fn around(center: &GeoPoint, radius_km: f64) -> Self {
+        let angular_radius = (radius_km / EARTH_RADIUS_KM).to_degrees();
+        let min_latitude = center.latitude() - angular_radius;
+        let max_latitude = center.latitude() + angular_radius;
+
+        // A circle around a pole spans every longitude
+        if min_latitude <= -90. || max_latitude >= 90. {
+            return BoundingBox {
+                min_latitude: min_latitude.max(-90.),
+                max_latitude: max_latitude.min(90.),
+                min_longitude: -180.,
+                max_longitude: 180.,
+            };
+        }
+
+        let delta_longitude = ((radius_km / EARTH_RADIUS_KM).sin() / center.latitude().to_radians().cos())
+            .min(1.)
+            .asin()
+            .to_degrees();
+        if delta_longitude >= 180. {
+            return BoundingBox {
+                min_latitude,
+                max_latitude,
+                min_longitude: -180.,
+                max_longitude: 180.,
+            };
+        }
+        BoundingBox {
+            min_latitude,
+            max_latitude,
+            min_longitude: normalize_longitude(center.longitude() - delta_longitude),
+            max_longitude: normalize_longitude(center.longitude() + delta_longitude),
+        }
+    }
+
+    pub fn contains(&self, point: &GeoPoint) -> bool {
+        let latitude_inside =
+            (self.min_latitude..=self.max_latitude).contains(&point.latitude());
+        let longitude_inside = if self.min_longitude <= self.max_longitude {
+            (self.min_longitude..=self.max_longitude).contains(&point.longitude())
+        } else {
+            point.longitude() >= self.min_longitude || point.longitude() <= self.max_longitude
+        };
+        latitude_inside && longitude_inside
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// Tolerance on distances, in km, well above the rounding errors of the formulas.
+    const EPSILON_KM: f64 = 1e-6;
+
+    fn point() -> impl Strategy<Value = GeoPoint> {
+        (-90. ..=90., -180. ..=180.).prop_map(|(latitude, longitude)| GeoPoint::new(latitude, longitude).unwrap())
+    }
+
+    /// Point reached by going `distance_km` from `start` with the given bearing, in radians.
+    fn destination(start: &GeoPoint, bearing: f64, distance_km: f64) -> GeoPoint {
+        let (latitude, longitude) = (start.latitude().to_radians(), start.longitude().to_radians());
+        let angle = distance_km / EARTH_RADIUS_KM;
+        let end_latitude =
+            (latitude.sin() * angle.cos() + latitude.cos() * angle.sin() * bearing.cos()).clamp(-1., 1.).asin();
+        let end_longitude = longitude
+            + (bearing.sin() * angle.sin() * latitude.cos())
+                .atan2(angle.cos() - latitude.sin() * end_latitude.sin());
+        GeoPoint::new(
+            end_latitude.to_degrees().clamp(-90., 90.),
+            normalize_longitude(end_longitude.to_degrees()),
+        )
+        .unwrap()
+    }
+
+    proptest! {
+        #[test]
+        fn distance_is_symmetric(a in point(), b in point()) {
+            prop_assert!((haversine_km(&a, &b) - haversine_km(&b, &a)).abs() < EPSILON_KM);
+        }
+
+        #[test]
+        fn distance_to_itself_is_zero(a in point()) {
+            prop_assert!(haversine_km(&a, &a).abs() < EPSILON_KM);
+        }
+
+        #[test]
+        fn distance_satisfies_the_triangle_inequality(a in point(), b in point(), c in point()) {
+            prop_assert!(haversine_km(&a, &c) <= haversine_km(&a, &b) + haversine_km(&b, &c) + EPSILON_KM);
+        }
+
+        #[test]
+        fn midpoint_is_halfway(a in point(), b in point()) {
+            let distance = haversine_km(&a, &b);
+            // Antipodal points have no single midpoint, and nearly antipodal ones an unstable one
+            prop_assume!(distance < 0.99 * PI * EARTH_RADIUS_KM);
+            let middle = midpoint(&a, &b);
+            prop_assert!((haversine_km(&a, &middle) - distance / 2.).abs() < 1e-3);
+            prop_assert!((haversine_km(&b, &middle) - distance / 2.).abs() < 1e-3);
+        }
+
+        #[test]
+        fn midpoint_across_the_antimeridian_stays_near_it(
+            latitude in -80. ..80.,
+            west in 170. ..=180.,
+            east in -180. ..-170.,
+        ) {
+            let a = GeoPoint::new(latitude, west).unwrap();
+            let b = GeoPoint::new(latitude, east).unwrap();
+            let middle = midpoint(&a, &b);
+            prop_assert!(middle.longitude().abs() >= 170., "{:?}", middle);
+            prop_assert!(haversine_km(&a, &middle) <= haversine_km(&a, &b));
+        }
+
+        #[test]
+        fn centroid_of_a_point_is_the_point(a in point()) {
+            prop_assert!(haversine_km(&centroid(&[a]), &a) < EPSILON_KM);
+        }
+
+        #[test]
+        fn centroid_of_two_points_is_their_midpoint(a in point(), b in point()) {
+            prop_assume!(haversine_km(&a, &b) < 0.99 * PI * EARTH_RADIUS_KM);
+            prop_assert!(haversine_km(&centroid(&[a, b]), &midpoint(&a, &b)) < 1e-3);
+        }
+
+        #[test]
+        fn centroid_is_within_the_circle_around_the_points(
+            center in point(),
+            offsets in prop::collection::vec((0. ..2. * PI, 0. ..1000.), 1..10),
+        ) {
+            let points: Vec<GeoPoint> = offsets
+                .iter()
+                .map(|(bearing, distance_km)| destination(&center, *bearing, *distance_km))
+                .collect();
+            prop_assert!(haversine_km(&centroid(&points), &center) <= 1000. + EPSILON_KM);
+        }
+
+        #[test]
+        fn bounding_box_contains_the_points_within_the_radius(
+            center in point(),
+            radius_km in 0.1..5000.,
+            bearing in 0. ..2. * PI,
+            fraction in 0. ..0.999,
+        ) {
+            let inside = destination(&center, bearing, radius_km * fraction);
+            prop_assert!(BoundingBox::around(&center, radius_km).contains(&inside), "{:?}", inside);
+        }
+    }
+
+    #[test]
+    fn midpoint_of_points_across_the_antimeridian() {
+        let middle = midpoint(&GeoPoint::new(0., 179.).unwrap(), &GeoPoint::new(0., -179.).unwrap());
+        assert!(middle.latitude().abs() < 1e-9);
+        assert!((middle.longitude().abs() - 180.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn bounding_box_around_a_pole_spans_every_longitude() {
+        let bounding_box = BoundingBox::around(&GeoPoint::new(89.9, 0.).unwrap(), 50.);
+        assert_eq!((bounding_box.min_longitude, bounding_box.max_longitude), (-180., 180.));
+        assert!(bounding_box.contains(&GeoPoint::new(89.9, 180.).unwrap()));
+    }
+}
@@ -0,0 +1,39 @@
+use async_trait::async_trait;
+use google_oauth::AsyncClient;
+use crate::AppError;
+
+use super::{IdentityProvider, VerifiedIdentity};
+
+/// Google Sign-In, validated through Google's certificates.
+pub struct GoogleProvider {
+    client: AsyncClient,
+}
+
+impl GoogleProvider {
+    pub fn new(client_id: String) -> Self {
+        GoogleProvider {
+            client: AsyncClient::new(client_id),
+        }
+    }
+}
+
+#[async_trait]
+impl IdentityProvider for GoogleProvider {
+    fn name(&self) -> &str {
+        "google"
+    }
+
+    async fn verify(&self, id_token: &str) -> Result<VerifiedIdentity, AppError> {
+        let payload = self.client.validate_id_token(id_token).await.map_err(|err| {
+            println!("Invalid Google ID token: {:?}", err);
+            AppError::AuthError
+        })?;
+
+        Ok(VerifiedIdentity {
+            subject: payload.sub,
+            email: payload.email,
+            email_verified: payload.email_verified.unwrap_or(false),
+            name: payload.name,
+        })
+    }
+}
@@ -0,0 +1,82 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use crate::{config::JwksSource, AppError, Config};
+
+mod google;
+mod oidc;
+
+pub use google::GoogleProvider;
+pub use oidc::OidcProvider;
+
+const APPLE_ISSUER: &str = "https://appleid.apple.com";
+const APPLE_JWKS_URL: &str = "https://appleid.apple.com/auth/keys";
+
+/// Provider selected by the client through the `provider` field of the login body.
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// Default, as it was the only provider supported by older clients.
+    #[default]
+    Google,
+    Apple,
+    Oidc,
+}
+
+/// Identity verified by a provider from an ID token.
+#[derive(Debug)]
+pub struct VerifiedIdentity {
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub name: Option<String>,
+}
+
+/// Verifies the ID tokens issued by an identity provider.
+#[async_trait]
+pub trait IdentityProvider: Send + Sync {
+    /// Name the provider is stored under in the identities of a user.
+    fn name(&self) -> &str;
+
+    async fn verify(&self, id_token: &str) -> Result<VerifiedIdentity, AppError>;
+}
+
+/// Identity providers enabled by the configuration. Built once along with the state of the functions,
+/// so that their keys are cached across requests.
+pub struct IdentityProviders {
+    google: Option<GoogleProvider>,
+    apple: Option<OidcProvider>,
+    oidc: Option<OidcProvider>,
+}
+
+impl IdentityProviders {
+    pub fn new(config: &Config) -> Self {
+        IdentityProviders {
+            google: config.google_client_id.clone().map(GoogleProvider::new),
+            apple: config.apple_client_id.clone().map(|client_id| {
+                OidcProvider::new(
+                    "apple",
+                    APPLE_ISSUER.to_owned(),
+                    client_id,
+                    JwksSource::Url(APPLE_JWKS_URL.to_owned()),
+                )
+            }),
+            oidc: config.oidc.clone().map(|oidc_config| {
+                OidcProvider::new(
+                    "oidc",
+                    oidc_config.issuer,
+                    oidc_config.client_id,
+                    oidc_config.jwks,
+                )
+            }),
+        }
+    }
+
+    /// Returns the requested provider, or `None` if it isn't configured.
+    pub fn get(&self, kind: ProviderKind) -> Option<&dyn IdentityProvider> {
+        match kind {
+            ProviderKind::Google => self.google.as_ref().map(|provider| provider as _),
+            ProviderKind::Apple => self.apple.as_ref().map(|provider| provider as _),
+            ProviderKind::Oidc => self.oidc.as_ref().map(|provider| provider as _),
+        }
+    }
+}
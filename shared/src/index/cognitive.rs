@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::{AppError, IndexAction};
+
+use super::{SearchResult, SearchResults, VectorIndex, VectorSearch};
+
+/// Up to this many excluded ids are sent in the filter, to stay within the limits on filter size.
+/// The search is widened by the number of ids left out, which are then dropped from the results.
+const MAX_FILTERED_IDS: usize = 500;
+
+/// Vector index backed by Azure Cognitive Search.
+/// Talks directly to the REST API, since the azure rust sdk lacks any data operation on the cognitive search index.
+pub struct CognitiveSearchIndex {
+    endpoint: String,
+    index_name: String,
+    admin_key: String,
+}
+
+impl CognitiveSearchIndex {
+    pub fn new(endpoint: String, index_name: String, admin_key: String) -> Self {
+        CognitiveSearchIndex {
+            endpoint,
+            index_name,
+            admin_key,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct VectorQuery {
+    kind: String,
+    vector: Vec<f64>,
+    fields: String,
+    k: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CognitiveQueryBody {
+    select: String,
+    filter: String,
+    vector_filter_mode: String,
+    vector_queries: Vec<VectorQuery>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CognitiveResponse {
+    #[serde(rename = "@odata.context")]
+    context: String,
+    value: Vec<CognitiveResponseValue>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CognitiveResponseValue {
+    #[serde(rename = "@search.score")]
+    search_score: f32,
+    id: String,
+    name: String,
+    description: Option<String>,
+}
+
+/// Builds the OData filter applied before the vector search. Like GeoJSON, the `POINT` literal takes
+/// the longitude first.
+fn search_filter(vector_search: &VectorSearch) -> String {
+    let mut filter = format!(
+        "geo.distance(location, geography'POINT({} {})') le {} and not blocked_user_ids/any(b: b eq '{}')",
+        vector_search.center.longitude(),
+        vector_search.center.latitude(),
+        vector_search.radius_km,
+        vector_search.viewer_id.replace('\'', "''")
+    );
+    if !vector_search.exclude_ids.is_empty() {
+        let excluded_ids = vector_search
+            .exclude_ids
+            .iter()
+            .take(MAX_FILTERED_IDS)
+            .map(|id| id.replace('\'', "''"))
+            .collect::<Vec<_>>()
+            .join(",");
+        filter = format!("not search.in(id, '{}', ',') and {}", excluded_ids, filter);
+    }
+    filter
+}
+
+#[async_trait]
+impl VectorIndex for CognitiveSearchIndex {
+    async fn index_documents(&self, index_actions: &[IndexAction]) -> Result<(), AppError> {
+        let mut map = HashMap::new();
+        map.insert("value", index_actions);
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!(
+                "{}/indexes('{}')/docs/search.index",
+                self.endpoint, self.index_name
+            ))
+            .query(&[("api-version", "2023-10-01-Preview")])
+            .header("api-key", &self.admin_key)
+            .json(&map)
+            .send()
+            .await?;
+        println!("Index response status: {}", response.status());
+
+        Ok(())
+    }
+
+    async fn search(&self, vector_search: &VectorSearch) -> Result<SearchResults, AppError> {
+        let unfiltered_ids = vector_search.exclude_ids.len().saturating_sub(MAX_FILTERED_IDS);
+        let cognitive_query_body = CognitiveQueryBody {
+            select: "id, name, description".to_owned(),
+            filter: search_filter(vector_search),
+            vector_filter_mode: "preFilter".to_owned(),
+            vector_queries: vec![VectorQuery {
+                kind: "vector".to_owned(),
+                vector: vector_search.vector.clone(),
+                fields: "description_embeddings".to_owned(),
+                k: vector_search.offset + vector_search.k + unfiltered_ids,
+            }],
+        };
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!(
+                "{}/indexes('{}')/docs/search.post.search",
+                self.endpoint, self.index_name
+            ))
+            .query(&[("api-version", "2023-10-01-Preview")])
+            .header("api-key", &self.admin_key)
+            .json(&cognitive_query_body)
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let cognitive_response = response.json::<CognitiveResponse>().await?;
+                Ok(SearchResults {
+                    value: cognitive_response
+                        .value
+                        .into_iter()
+                        .filter(|value| !vector_search.exclude_ids.contains(&value.id))
+                        .filter(|value| value.search_score >= vector_search.min_score.unwrap_or(f32::MIN))
+                        .skip(vector_search.offset)
+                        .take(vector_search.k)
+                        .map(|value| SearchResult {
+                            search_score: value.search_score,
+                            id: value.id,
+                            name: value.name,
+                            description: value.description.unwrap_or_default(),
+                        })
+                        .collect(),
+                })
+            }
+            _ => Err(AppError::GenericError),
+        }
+    }
+}
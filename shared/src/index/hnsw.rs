@@ -0,0 +1,314 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashSet},
+};
+
+use rand::Rng;
+
+/// Hierarchical Navigable Small World graph (https://arxiv.org/abs/1603.09320) over cosine distance.
+/// Used by the local index to answer approximate nearest neighbours queries without scanning every vector.
+/// Removed nodes are only marked as deleted, so the graph should be rebuilt once they pile up.
+pub struct Hnsw {
+    /// Maximum number of connections per node on the upper layers.
+    m: usize,
+    /// Maximum number of connections per node on layer 0.
+    m_max0: usize,
+    ef_construction: usize,
+    level_multiplier: f64,
+    nodes: Vec<Node>,
+    entry_point: Option<usize>,
+    deleted_count: usize,
+}
+
+struct Node {
+    vector: Vec<f64>,
+    /// Neighbours of the node for each layer it belongs to, from layer 0 upwards.
+    neighbours: Vec<Vec<usize>>,
+    deleted: bool,
+}
+
+/// Candidate node paired with its distance from the query, ordered by distance.
+#[derive(Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f64,
+    node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+impl Default for Hnsw {
+    fn default() -> Self {
+        Hnsw::new(16, 200)
+    }
+}
+
+impl Hnsw {
+    pub fn new(m: usize, ef_construction: usize) -> Self {
+        Hnsw {
+            m,
+            m_max0: m * 2,
+            ef_construction,
+            level_multiplier: 1. / (m as f64).ln(),
+            nodes: Vec::new(),
+            entry_point: None,
+            deleted_count: 0,
+        }
+    }
+
+    /// Number of live (not deleted) nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len() - self.deleted_count
+    }
+
+    /// Number of nodes, deleted ones included, i.e. the most a search can ever visit.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn deleted_count(&self) -> usize {
+        self.deleted_count
+    }
+
+    /// Adds a vector to the graph, returning the id of the new node.
+    pub fn insert(&mut self, vector: &[f64]) -> usize {
+        let vector = normalize(vector);
+        let level = (-rand::thread_rng().gen::<f64>().ln() * self.level_multiplier).floor() as usize;
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            vector,
+            neighbours: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+
+        let Some(entry_point) = self.entry_point else {
+            self.entry_point = Some(node);
+            return node;
+        };
+        let top_level = self.nodes[entry_point].neighbours.len() - 1;
+        let query = self.nodes[node].vector.clone();
+
+        // Greedily descend the layers above the new node's level
+        let mut entry_points = vec![entry_point];
+        for layer in (level + 1..=top_level).rev() {
+            entry_points = vec![self.search_layer(&query, &entry_points, 1, layer)[0].node];
+        }
+
+        for layer in (0..=level.min(top_level)).rev() {
+            let candidates = self.search_layer(&query, &entry_points, self.ef_construction, layer);
+            let max_connections = if layer == 0 { self.m_max0 } else { self.m };
+            let neighbours: Vec<usize> = candidates
+                .iter()
+                .take(self.m)
+                .map(|candidate| candidate.node)
+                .collect();
+
+            for &neighbour in &neighbours {
+                self.nodes[neighbour].neighbours[layer].push(node);
+                if self.nodes[neighbour].neighbours[layer].len() > max_connections {
+                    self.shrink_connections(neighbour, layer, max_connections);
+                }
+            }
+            self.nodes[node].neighbours[layer] = neighbours;
+            entry_points = candidates.iter().map(|candidate| candidate.node).collect();
+        }
+
+        if level > top_level {
+            self.entry_point = Some(node);
+        }
+        node
+    }
+
+    /// Marks a node as deleted. It still helps navigating the graph, but is never returned by searches.
+    pub fn remove(&mut self, node: usize) {
+        if !self.nodes[node].deleted {
+            self.nodes[node].deleted = true;
+            self.deleted_count += 1;
+        }
+    }
+
+    /// Returns up to `ef` live nodes close to the query, sorted by ascending cosine distance.
+    pub fn search(&self, query: &[f64], ef: usize) -> Vec<(usize, f64)> {
+        let Some(entry_point) = self.entry_point else {
+            return Vec::new();
+        };
+        let query = normalize(query);
+        let top_level = self.nodes[entry_point].neighbours.len() - 1;
+
+        let mut entry_points = vec![entry_point];
+        for layer in (1..=top_level).rev() {
+            entry_points = vec![self.search_layer(&query, &entry_points, 1, layer)[0].node];
+        }
+
+        self.search_layer(&query, &entry_points, ef, 0)
+            .into_iter()
+            .filter(|candidate| !self.nodes[candidate.node].deleted)
+            .map(|candidate| (candidate.node, candidate.distance))
+            .collect()
+    }
+
+    /// Best-first search on a single layer, returning the `ef` closest nodes found sorted by distance.
+    fn search_layer(
+        &self,
+        query: &[f64],
+        entry_points: &[usize],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<Candidate> {
+        let mut visited: HashSet<usize> = entry_points.iter().copied().collect();
+        let mut candidates = BinaryHeap::new();
+        let mut closest = BinaryHeap::new();
+
+        for &node in entry_points {
+            let candidate = Candidate {
+                distance: cosine_distance(query, &self.nodes[node].vector),
+                node,
+            };
+            candidates.push(Reverse(candidate));
+            closest.push(candidate);
+        }
+        while closest.len() > ef {
+            closest.pop();
+        }
+
+        while let Some(Reverse(candidate)) = candidates.pop() {
+            let furthest = closest.peek().map_or(f64::INFINITY, |c: &Candidate| c.distance);
+            if candidate.distance > furthest && closest.len() >= ef {
+                break;
+            }
+
+            for &neighbour in &self.nodes[candidate.node].neighbours[layer] {
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+                let distance = cosine_distance(query, &self.nodes[neighbour].vector);
+                let furthest = closest.peek().map_or(f64::INFINITY, |c: &Candidate| c.distance);
+                if closest.len() < ef || distance < furthest {
+                    let neighbour = Candidate {
+                        distance,
+                        node: neighbour,
+                    };
+                    candidates.push(Reverse(neighbour));
+                    closest.push(neighbour);
+                    if closest.len() > ef {
+                        closest.pop();
+                    }
+                }
+            }
+        }
+
+        closest.into_sorted_vec()
+    }
+
+    /// Keeps only the closest `max_connections` neighbours of a node on the given layer.
+    fn shrink_connections(&mut self, node: usize, layer: usize, max_connections: usize) {
+        let vector = &self.nodes[node].vector;
+        let mut neighbours: Vec<Candidate> = self.nodes[node].neighbours[layer]
+            .iter()
+            .map(|&neighbour| Candidate {
+                distance: cosine_distance(vector, &self.nodes[neighbour].vector),
+                node: neighbour,
+            })
+            .collect();
+        neighbours.sort();
+        neighbours.truncate(max_connections);
+        self.nodes[node].neighbours[layer] = neighbours.into_iter().map(|c| c.node).collect();
+    }
+}
+
+fn normalize(vector: &[f64]) -> Vec<f64> {
+    let norm = vector.iter().map(|value| value * value).sum::<f64>().sqrt();
+    if norm == 0. {
+        return vector.to_vec();
+    }
+    vector.iter().map(|value| value / norm).collect()
+}
+
+/// Cosine distance between two already normalized vectors.
+fn cosine_distance(a: &[f64], b: &[f64]) -> f64 {
+    1. - a.iter().zip(b).map(|(a, b)| a * b).sum::<f64>()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn random_vectors(count: usize, dimensions: usize, rng: &mut StdRng) -> Vec<Vec<f64>> {
+        (0..count)
+            .map(|_| (0..dimensions).map(|_| rng.gen_range(-1. ..1.)).collect())
+            .collect()
+    }
+
+    fn exact_nearest(vectors: &[Vec<f64>], query: &[f64], k: usize) -> Vec<usize> {
+        let query = normalize(query);
+        let mut distances: Vec<(usize, f64)> = vectors
+            .iter()
+            .enumerate()
+            .map(|(node, vector)| (node, cosine_distance(&query, &normalize(vector))))
+            .collect();
+        distances.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        distances.into_iter().take(k).map(|(node, _)| node).collect()
+    }
+
+    #[test]
+    fn search_recalls_most_of_the_exact_nearest_neighbours() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let vectors = random_vectors(2000, 16, &mut rng);
+        let mut graph = Hnsw::default();
+        for vector in &vectors {
+            graph.insert(vector);
+        }
+
+        let k = 10;
+        let mut found = 0;
+        for query in random_vectors(50, 16, &mut rng) {
+            let exact: HashSet<usize> = exact_nearest(&vectors, &query, k).into_iter().collect();
+            let results = graph.search(&query, 64);
+            assert!(results.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+            found += results.iter().take(k).filter(|(node, _)| exact.contains(node)).count();
+        }
+        let recall = found as f64 / (50 * k) as f64;
+        assert!(recall >= 0.9, "recall {}", recall);
+    }
+
+    #[test]
+    fn deleted_nodes_are_never_returned() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let vectors = random_vectors(200, 8, &mut rng);
+        let mut graph = Hnsw::default();
+        for vector in &vectors {
+            graph.insert(vector);
+        }
+        for node in (0..200).step_by(2) {
+            graph.remove(node);
+        }
+        graph.remove(0);
+
+        assert_eq!(graph.len(), 100);
+        assert_eq!(graph.deleted_count(), 100);
+        assert_eq!(graph.node_count(), 200);
+        let results = graph.search(&vectors[0], graph.node_count());
+        assert_eq!(results.len(), 100);
+        assert!(results.iter().all(|(node, _)| node % 2 == 1));
+    }
+
+    #[test]
+    fn empty_graph_finds_nothing() {
+        assert!(Hnsw::default().search(&[1., 0.], 10).is_empty());
+    }
+}
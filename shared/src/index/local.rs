@@ -0,0 +1,437 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::RwLock,
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    geodesy::{haversine_km, BoundingBox},
+    AppError, IndexAction, IndexActionType, UserSearchData,
+};
+
+use super::{hnsw::Hnsw, SearchResult, SearchResults, VectorIndex, VectorSearch};
+
+/// Up to this many documents passing the geo-distance filter, searches compare the query against each of them.
+/// Above it, the HNSW graph is used to only visit the most promising ones.
+const EXACT_SEARCH_LIMIT: usize = 2000;
+
+/// In-process vector index, meant for running the functions offline and for tests.
+/// Like Cognitive Search, results are scored as `1 / (1 + cosine distance)`.
+#[derive(Default)]
+pub struct LocalVectorIndex {
+    state: RwLock<IndexState>,
+}
+
+#[derive(Default)]
+struct IndexState {
+    documents: HashMap<String, IndexedDocument>,
+    graph: Hnsw,
+    /// Id of the document each graph node was created for.
+    node_ids: Vec<String>,
+}
+
+struct IndexedDocument {
+    data: UserSearchData,
+    /// Graph node holding the document's current embeddings, if any.
+    node: Option<usize>,
+}
+
+impl IndexState {
+    fn upload(&mut self, data: UserSearchData) {
+        self.delete(&data.id);
+        let node = data
+            .description_embeddings
+            .as_ref()
+            .map(|embeddings| self.insert_node(&data.id, embeddings));
+        self.documents
+            .insert(data.id.clone(), IndexedDocument { data, node });
+    }
+
+    /// Updates the fields set in `data`, leaving the others untouched.
+    fn merge(&mut self, data: UserSearchData) {
+        let Some(document) = self.documents.get_mut(&data.id) else {
+            println!("Document {} not found, skipping merge", data.id);
+            return;
+        };
+        document.data.name = data.name;
+        document.data.blocked_user_ids = data.blocked_user_ids;
+        if data.description.is_some() {
+            document.data.description = data.description;
+        }
+        if data.location.is_some() {
+            document.data.location = data.location;
+        }
+        if let Some(embeddings) = data.description_embeddings {
+            if let Some(node) = document.node.take() {
+                self.graph.remove(node);
+            }
+            document.data.description_embeddings = Some(embeddings.clone());
+            let id = document.data.id.clone();
+            let node = self.insert_node(&id, &embeddings);
+            self.documents.get_mut(&id).unwrap().node = Some(node);
+        }
+    }
+
+    fn delete(&mut self, id: &str) {
+        if let Some(IndexedDocument {
+            node: Some(node), ..
+        }) = self.documents.remove(id)
+        {
+            self.graph.remove(node);
+        }
+        // Rebuild the graph once deleted nodes outnumber the live ones, so searches don't waste time on them
+        if self.graph.deleted_count() > self.graph.len().max(EXACT_SEARCH_LIMIT) {
+            self.rebuild_graph();
+        }
+    }
+
+    fn insert_node(&mut self, id: &str, embeddings: &[f64]) -> usize {
+        let node = self.graph.insert(embeddings);
+        self.node_ids.push(id.to_owned());
+        node
+    }
+
+    fn rebuild_graph(&mut self) {
+        self.graph = Hnsw::default();
+        self.node_ids.clear();
+        let ids: Vec<String> = self.documents.keys().cloned().collect();
+        for id in ids {
+            let embeddings = self.documents[&id].data.description_embeddings.clone();
+            let node = embeddings.map(|embeddings| self.insert_node(&id, &embeddings));
+            self.documents.get_mut(&id).unwrap().node = node;
+        }
+    }
+
+    /// Nearest neighbours among the candidates, found through the HNSW graph.
+    /// The search is widened until enough candidates are found or the whole graph has been explored.
+    fn approximate_search(
+        &self,
+        vector: &[f64],
+        k: usize,
+        candidates: &HashSet<&str>,
+    ) -> Vec<(String, f64)> {
+        let mut ef = (k * 4).max(64);
+        loop {
+            let results: Vec<(String, f64)> = self
+                .graph
+                .search(vector, ef)
+                .into_iter()
+                .map(|(node, distance)| (self.node_ids[node].clone(), distance))
+                .filter(|(id, _)| candidates.contains(id.as_str()))
+                .take(k)
+                .collect();
+            // Deleted nodes take up room in the results too, so only stop once every node was reachable
+            if results.len() >= k || ef >= self.graph.node_count() {
+                return results;
+            }
+            ef *= 2;
+        }
+    }
+}
+
+fn cosine_distance(a: &[f64], b: &[f64]) -> f64 {
+    let dot: f64 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let norm_a = a.iter().map(|value| value * value).sum::<f64>().sqrt();
+    let norm_b = b.iter().map(|value| value * value).sum::<f64>().sqrt();
+    if norm_a == 0. || norm_b == 0. {
+        return 1.;
+    }
+    1. - dot / (norm_a * norm_b)
+}
+
+#[async_trait]
+impl VectorIndex for LocalVectorIndex {
+    async fn index_documents(&self, index_actions: &[IndexAction]) -> Result<(), AppError> {
+        let mut state = self.state.write().unwrap();
+        for index_action in index_actions {
+            let data = index_action.user_document.clone();
+            match index_action.action_type {
+                IndexActionType::Upload => state.upload(data),
+                IndexActionType::Merge => state.merge(data),
+                IndexActionType::MergeOrUpload => {
+                    if state.documents.contains_key(&data.id) {
+                        state.merge(data)
+                    } else {
+                        state.upload(data)
+                    }
+                }
+                IndexActionType::Delete => state.delete(&data.id),
+            }
+        }
+        Ok(())
+    }
+
+    async fn search(&self, vector_search: &VectorSearch) -> Result<SearchResults, AppError> {
+        let state = self.state.read().unwrap();
+        let bounding_box = BoundingBox::around(&vector_search.center, vector_search.radius_km);
+
+        let candidates: Vec<&UserSearchData> = state
+            .documents
+            .values()
+            .map(|document| &document.data)
+            .filter(|data| !vector_search.exclude_ids.contains(&data.id))
+            .filter(|data| !data.blocked_user_ids.contains(&vector_search.viewer_id))
+            .filter(|data| data.description_embeddings.is_some())
+            .filter(|data| match &data.location {
+                Some(location) => {
+                    bounding_box.contains(location)
+                        && haversine_km(&vector_search.center, location) <= vector_search.radius_km
+                }
+                None => false,
+            })
+            .collect();
+
+        let nearest: Vec<(String, f64)> = if candidates.len() <= EXACT_SEARCH_LIMIT {
+            let mut distances: Vec<(String, f64)> = candidates
+                .iter()
+                .map(|data| {
+                    let embeddings = data.description_embeddings.as_ref().unwrap();
+                    (data.id.clone(), cosine_distance(&vector_search.vector, embeddings))
+                })
+                .collect();
+            distances.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+            distances.truncate(vector_search.offset + vector_search.k);
+            distances
+        } else {
+            let candidate_ids = candidates.iter().map(|data| data.id.as_str()).collect();
+            state.approximate_search(
+                &vector_search.vector,
+                vector_search.offset + vector_search.k,
+                &candidate_ids,
+            )
+        };
+
+        Ok(SearchResults {
+            value: nearest
+                .into_iter()
+                .map(|(id, distance)| {
+                    let data = &state.documents[&id].data;
+                    SearchResult {
+                        search_score: (1. / (1. + distance)) as f32,
+                        id,
+                        name: data.name.clone(),
+                        description: data.description.clone().unwrap_or_default(),
+                    }
+                })
+                .filter(|result| result.search_score >= vector_search.min_score.unwrap_or(f32::MIN))
+                .skip(vector_search.offset)
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::GeoPoint;
+
+    use super::*;
+
+    fn document(id: &str, embeddings: Vec<f64>, location: GeoPoint) -> UserSearchData {
+        UserSearchData {
+            id: id.to_owned(),
+            name: format!("User {}", id),
+            description: Some(format!("Description of {}", id)),
+            description_embeddings: Some(embeddings),
+            location: Some(location),
+            blocked_user_ids: Vec::new(),
+        }
+    }
+
+    fn action(action_type: IndexActionType, user_document: UserSearchData) -> IndexAction {
+        IndexAction {
+            action_type,
+            user_document,
+        }
+    }
+
+    fn milan() -> GeoPoint {
+        GeoPoint::new(45.4642, 9.19).unwrap()
+    }
+
+    fn search(vector: Vec<f64>, k: usize) -> VectorSearch {
+        VectorSearch {
+            vector,
+            k,
+            center: milan(),
+            radius_km: 5.,
+            exclude_ids: Vec::new(),
+            viewer_id: "viewer".to_owned(),
+            offset: 0,
+            min_score: None,
+        }
+    }
+
+    fn ids(results: &SearchResults) -> Vec<&str> {
+        results.value.iter().map(|result| result.id.as_str()).collect()
+    }
+
+    fn random_state(count: usize, rng: &mut StdRng) -> IndexState {
+        let mut state = IndexState::default();
+        for i in 0..count {
+            let embeddings = (0..8).map(|_| rng.gen_range(-1. ..1.)).collect();
+            state.upload(document(&i.to_string(), embeddings, milan()));
+        }
+        state
+    }
+
+    fn exact_nearest(state: &IndexState, vector: &[f64], k: usize) -> Vec<String> {
+        let mut distances: Vec<(String, f64)> = state
+            .documents
+            .values()
+            .map(|document| {
+                let embeddings = document.data.description_embeddings.as_ref().unwrap();
+                (document.data.id.clone(), cosine_distance(vector, embeddings))
+            })
+            .collect();
+        distances.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        distances.into_iter().take(k).map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn approximate_search_recalls_most_of_the_exact_results() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let state = random_state(1000, &mut rng);
+        let candidates = state.documents.keys().map(String::as_str).collect();
+
+        let k = 10;
+        let mut found = 0;
+        for _ in 0..30 {
+            let vector: Vec<f64> = (0..8).map(|_| rng.gen_range(-1. ..1.)).collect();
+            let exact = exact_nearest(&state, &vector, k);
+            let approximate = state.approximate_search(&vector, k, &candidates);
+            assert_eq!(approximate.len(), k);
+            found += approximate.iter().filter(|(id, _)| exact.contains(id)).count();
+        }
+        let recall = found as f64 / (30 * k) as f64;
+        assert!(recall >= 0.9, "recall {}", recall);
+    }
+
+    #[test]
+    fn approximate_search_finds_k_live_documents_among_many_deleted_ones() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut state = random_state(300, &mut rng);
+        for i in 0..290 {
+            state.delete(&i.to_string());
+        }
+        assert_eq!(state.graph.len(), 10);
+        assert_eq!(state.graph.deleted_count(), 290);
+
+        let candidates = state.documents.keys().map(String::as_str).collect();
+        let results = state.approximate_search(&[1.; 8], 10, &candidates);
+        assert_eq!(results.len(), 10);
+    }
+
+    #[test]
+    fn merge_replaces_embeddings_and_keeps_unset_fields() {
+        let mut state = IndexState::default();
+        state.upload(document("a", vec![1., 0.], milan()));
+        state.merge(UserSearchData {
+            id: "a".to_owned(),
+            name: "Renamed".to_owned(),
+            description: None,
+            description_embeddings: Some(vec![0., 1.]),
+            location: None,
+            blocked_user_ids: vec!["b".to_owned()],
+        });
+
+        let data = &state.documents["a"].data;
+        assert_eq!(data.name, "Renamed");
+        assert_eq!(data.description.as_deref(), Some("Description of a"));
+        assert_eq!(data.location, Some(milan()));
+        assert_eq!(data.blocked_user_ids, ["b"]);
+        assert_eq!(state.graph.len(), 1);
+        assert_eq!(state.graph.deleted_count(), 1);
+
+        let candidates = HashSet::from(["a"]);
+        let results = state.approximate_search(&[0., 1.], 1, &candidates);
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.abs() < 1e-9);
+    }
+
+    #[test]
+    fn merge_of_an_unknown_document_is_skipped() {
+        let mut state = IndexState::default();
+        state.merge(document("a", vec![1., 0.], milan()));
+        assert!(state.documents.is_empty());
+        assert_eq!(state.graph.node_count(), 0);
+    }
+
+    #[test]
+    fn rebuild_drops_deleted_nodes() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut state = random_state(50, &mut rng);
+        for i in 0..20 {
+            state.delete(&i.to_string());
+        }
+        state.rebuild_graph();
+
+        assert_eq!(state.graph.len(), 30);
+        assert_eq!(state.graph.deleted_count(), 0);
+        assert_eq!(state.node_ids.len(), 30);
+        for (id, document) in &state.documents {
+            assert_eq!(&state.node_ids[document.node.unwrap()], id);
+        }
+    }
+
+    #[tokio::test]
+    async fn search_leaves_out_documents_outside_the_radius() {
+        let index = LocalVectorIndex::default();
+        let turin = GeoPoint::new(45.0703, 7.6869).unwrap();
+        let nearby = GeoPoint::new(45.48, 9.2).unwrap();
+        let mut unlocated = document("unlocated", vec![1., 0.], milan());
+        unlocated.location = None;
+        index
+            .index_documents(&[
+                action(IndexActionType::Upload, document("near", vec![0., 1.], nearby)),
+                action(IndexActionType::Upload, document("far", vec![1., 0.], turin)),
+                action(IndexActionType::Upload, unlocated),
+            ])
+            .await
+            .unwrap();
+
+        let results = index.search(&search(vec![1., 0.], 3)).await.unwrap();
+        assert_eq!(ids(&results), ["near"]);
+    }
+
+    #[tokio::test]
+    async fn search_leaves_out_excluded_users_and_those_who_blocked_the_viewer() {
+        let index = LocalVectorIndex::default();
+        let mut blocker = document("blocker", vec![1., 0.], milan());
+        blocker.blocked_user_ids = vec!["viewer".to_owned()];
+        index
+            .index_documents(&[
+                action(IndexActionType::Upload, document("closest", vec![1., 0.], milan())),
+                action(IndexActionType::Upload, document("other", vec![0., 1.], milan())),
+                action(IndexActionType::Upload, blocker),
+            ])
+            .await
+            .unwrap();
+
+        let results = index.search(&search(vec![1., 0.], 3)).await.unwrap();
+        assert_eq!(ids(&results), ["closest", "other"]);
+
+        let mut excluding = search(vec![1., 0.], 3);
+        excluding.exclude_ids = vec!["closest".to_owned()];
+        let results = index.search(&excluding).await.unwrap();
+        assert_eq!(ids(&results), ["other"]);
+    }
+
+    #[tokio::test]
+    async fn deleted_documents_are_no_longer_found() {
+        let index = LocalVectorIndex::default();
+        index
+            .index_documents(&[
+                action(IndexActionType::Upload, document("a", vec![1., 0.], milan())),
+                action(IndexActionType::Upload, document("b", vec![0., 1.], milan())),
+                action(IndexActionType::Delete, document("a", vec![1., 0.], milan())),
+            ])
+            .await
+            .unwrap();
+
+        let results = index.search(&search(vec![1., 0.], 3)).await.unwrap();
+        assert_eq!(ids(&results), ["b"]);
+    }
+}
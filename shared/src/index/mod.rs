@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::VectorIndexConfig, AppError, GeoPoint, IndexAction, UserSearchData};
+
+mod cognitive;
+mod hnsw;
+mod local;
+
+pub use cognitive::CognitiveSearchIndex;
+pub use local::LocalVectorIndex;
+
+/// Maximum distance (in km) between two users for them to be considered a match.
+pub const DEFAULT_SEARCH_RADIUS_KM: f64 = 5.;
+/// Bounds of the search radius clients can ask for.
+pub const MIN_SEARCH_RADIUS_KM: f64 = 1.;
+pub const MAX_SEARCH_RADIUS_KM: f64 = 100.;
+/// Number of similar users returned by a search.
+pub const DEFAULT_SEARCH_RESULTS: usize = 3;
+pub const MAX_SEARCH_RESULTS: usize = 20;
+/// Results further than this in the ranking are never returned, since every page of a search runs
+/// the nearest neighbours search again down to its last result.
+pub const MAX_SEARCH_DEPTH: usize = 200;
+
+/// Vector index holding the searchable data of each user (`UserSearchData`).
+/// Functions only talk to the index through this trait, so Cognitive Search can be swapped for an
+/// in-process index when running offline.
+#[async_trait]
+pub trait VectorIndex: Send + Sync {
+    /// Applies a batch of upload/merge/delete actions to the index.
+    async fn index_documents(&self, index_actions: &[IndexAction]) -> Result<(), AppError>;
+
+    /// Runs a k-nearest-neighbours search on the description embeddings, only considering documents
+    /// located within the search radius. Returns up to `k` results, after skipping the first `offset` ones.
+    async fn search(&self, vector_search: &VectorSearch) -> Result<SearchResults, AppError>;
+}
+
+/// A k-nearest-neighbours query with a geo-distance pre-filter.
+#[derive(Clone, Debug)]
+pub struct VectorSearch {
+    pub vector: Vec<f64>,
+    pub k: usize,
+    pub center: GeoPoint,
+    pub radius_km: f64,
+    /// Documents to leave out of the results, e.g. the user performing the search.
+    pub exclude_ids: Vec<String>,
+    /// User performing the search, users who blocked them are left out of the results.
+    pub viewer_id: String,
+    /// Number of best results to skip, for the following pages of a search.
+    pub offset: usize,
+    /// Results scoring less than this are left out.
+    pub min_score: Option<f32>,
+}
+
+impl VectorSearch {
+    /// Builds the default search for users close to and similar to the given one, leaving out the
+    /// users they blocked.
+    pub fn for_user(user_search_data: &UserSearchData) -> Result<Self, AppError> {
+        let center = user_search_data
+            .location
+            .ok_or(AppError::MissingLocationData)?;
+        let vector = user_search_data
+            .description_embeddings
+            .clone()
+            .ok_or(AppError::NotFoundError)?;
+
+        Ok(VectorSearch {
+            vector,
+            k: DEFAULT_SEARCH_RESULTS,
+            center,
+            radius_km: DEFAULT_SEARCH_RADIUS_KM,
+            exclude_ids: [user_search_data.id.clone()]
+                .into_iter()
+                .chain(user_search_data.blocked_user_ids.iter().cloned())
+                .collect(),
+            viewer_id: user_search_data.id.clone(),
+            offset: 0,
+            min_score: None,
+        })
+    }
+}
+
+/// Search results, serialized in the same shape as the Cognitive Search response.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SearchResults {
+    pub value: Vec<SearchResult>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SearchResult {
+    #[serde(rename = "@search.score")]
+    pub search_score: f32,
+    pub id: String,
+    pub name: String,
+    pub description: String,
+}
+
+/// Builds the configured vector index.
+pub fn get_vector_index(vector_index_config: &VectorIndexConfig) -> Arc<dyn VectorIndex> {
+    match vector_index_config {
+        VectorIndexConfig::Cognitive {
+            endpoint,
+            index_name,
+            admin_key,
+        } => Arc::new(CognitiveSearchIndex::new(
+            endpoint.clone(),
+            index_name.clone(),
+            admin_key.clone(),
+        )),
+        VectorIndexConfig::Local => {
+            println!("Using the local vector index, data will be lost on shutdown");
+            Arc::new(LocalVectorIndex::default())
+        }
+    }
+}
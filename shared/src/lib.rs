@@ -1,27 +1,53 @@
-use anyhow::Error;
 use axum::{
     response::{IntoResponse, Response},
     Json,
 };
 use azure_data_cosmos::prelude::{
-    AuthorizationToken, CloudLocation, CollectionClient, CosmosClient, Param, Query, GetDocumentResponse,
+    AuthorizationToken, CollectionClient, CosmosClient,
 };
-use base64::engine::general_purpose::STANDARD_NO_PAD;
-use base64::Engine;
-use futures::{stream, StreamExt};
 use hyper::StatusCode;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::{
-    collections::HashMap,
-    env::VarError,
-    error::Error as StdError,
-    fmt::{self, Display},
-};
-use log::log;
-use crate::AppError::NotFoundError;
-
-#[derive(Serialize, Deserialize, Clone, Debug)]
+use std::env::VarError;
+use crate::config::CosmosConfig;
+
+pub mod config;
+pub mod embeddings;
+pub mod extract;
+pub mod geo;
+pub mod geodesy;
+pub mod identity;
+pub mod index;
+pub mod matches;
+pub mod meetings;
+pub mod meetups;
+pub mod migrations;
+pub mod pois;
+pub mod profile;
+pub mod recommendation;
+pub mod reports;
+pub mod server;
+pub mod session;
+pub mod store;
+pub mod visibility;
+
+pub use config::Config;
+pub use embeddings::{get_embedding_provider, EmbeddingProvider};
+pub use geo::{GeoPoint, InvalidCoordinates};
+pub use index::{get_vector_index, SearchResults, VectorIndex, VectorSearch};
+pub use matches::{get_match_store, MatchPage, MatchRecord, MatchStore};
+pub use meetings::{get_meeting_store, Meeting, MeetingStatus, MeetingStore, TimeSlot};
+pub use meetups::{get_meetup_store, Meetup, MeetupStore};
+pub use pois::{get_poi_store, NearbyPoi, Poi, PoiCategory, PoiStore};
+pub use profile::{MatchView, MatchViewPage, MyProfile, PublicProfile};
+pub use recommendation::RecommendationTicket;
+pub use reports::{get_report_store, Report, ReportReason, ReportStore};
+pub use server::{custom_handler_address, serve, AppState};
+pub use session::Session;
+pub use store::{get_user_store, retry_on_conflict, update_user, UserStore, Versioned};
+pub use visibility::{location_precision, LocationPrecision};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub enum MatchStatus {
     /// Requires an action from the user the match was sent to.
     Pending = 1,
@@ -31,31 +57,90 @@ pub enum MatchStatus {
     Accepted = 3,
     /// Discarded match, could be useful in future to prevent matching the same user again.
     Denied = 4,
+    /// Withdrawn by the sender before an answer, or undone by either user once accepted.
+    /// Unlike a denied match, the users can match again.
+    Cancelled = 5,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct Match {
-    pub id: String,
-    pub name: String,
-    pub description: String,
-    pub match_status: MatchStatus,
+/// Change of a match requested by one of its users.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchAction {
+    Accept,
+    Reject,
+    Cancel,
+}
+
+/// A change a user isn't allowed to perform on a match in its current status.
+#[derive(Debug)]
+pub struct IllegalTransition {
+    pub from: MatchStatus,
+    pub action: MatchAction,
+}
+
+impl MatchStatus {
+    /// Status of the same match as seen by the other user.
+    pub fn mirrored(&self) -> MatchStatus {
+        match self {
+            MatchStatus::Pending => MatchStatus::AwaitingUserAction,
+            MatchStatus::AwaitingUserAction => MatchStatus::Pending,
+            MatchStatus::Accepted => MatchStatus::Accepted,
+            MatchStatus::Denied => MatchStatus::Denied,
+            MatchStatus::Cancelled => MatchStatus::Cancelled,
+        }
+    }
+
+    /// Status after a user performs an action on a match which has this status from their point of view.
+    /// Only the recipient can answer a request, only the sender can withdraw it, and either user
+    /// can undo an accepted match. Denied and cancelled matches are final.
+    pub fn transition(&self, action: MatchAction) -> Result<MatchStatus, IllegalTransition> {
+        match (self, action) {
+            (MatchStatus::AwaitingUserAction, MatchAction::Accept) => Ok(MatchStatus::Accepted),
+            (MatchStatus::AwaitingUserAction, MatchAction::Reject) => Ok(MatchStatus::Denied),
+            (MatchStatus::Pending, MatchAction::Cancel) => Ok(MatchStatus::Cancelled),
+            (MatchStatus::Accepted, MatchAction::Cancel) => Ok(MatchStatus::Cancelled),
+            (from, action) => Err(IllegalTransition {
+                from: from.clone(),
+                action,
+            }),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct UserDocument {
     pub id: String,
+    /// Version of the document layout, see `migrations`. Missing on documents predating versioning.
+    #[serde(default)]
+    pub schema_version: u32,
     pub email: String,
     pub name: String,
-    pub access_token: String,
+    /// Accounts of identity providers the user can log in with.
+    #[serde(default)]
+    pub identities: Vec<Identity>,
+    /// Active sessions, one per logged in device.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sessions: Vec<Session>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description_embeddings: Option<Vec<f64>>,
     // todo vec length is constant, we can optimize this
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub location: Option<Point>,
-    /// Denotes matches related to this user.
-    pub matches: Vec<Match>,
+    pub location: Option<GeoPoint>,
+    /// Users this user blocked. They are never recommended to each other, nor can they match.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_user_ids: Vec<String>,
+    /// Hash of the token in the URL of the calendar feed of the user, who has none when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calendar_feed_token_hash: Option<String>,
+}
+
+/// An account of an identity provider (e.g. Google or Apple) linked to a user.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Identity {
+    pub provider: String,
+    /// Identifier of the account, unique within the provider (the `sub` claim).
+    pub subject: String,
 }
 
 impl azure_data_cosmos::CosmosEntity for UserDocument {
@@ -76,7 +161,10 @@ pub struct UserSearchData {
     pub description_embeddings: Option<Vec<f64>>,
     // todo vec length is constant, we can optimize this
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub location: Option<Point>,
+    pub location: Option<GeoPoint>,
+    /// Always sent, so that merges don't keep a stale list in the index.
+    #[serde(default)]
+    pub blocked_user_ids: Vec<String>,
 }
 
 impl From<UserDocument> for UserSearchData {
@@ -87,37 +175,16 @@ impl From<UserDocument> for UserSearchData {
             description: user_doc.description,
             description_embeddings: user_doc.description_embeddings,
             location: user_doc.location,
+            blocked_user_ids: user_doc.blocked_user_ids,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct Point {
-    #[serde(rename = "type")]
-    r#type: String,
-    pub coordinates: [f64; 2],
-}
-
-impl Point {
-    pub fn new(latitude: f64, longitude: f64) -> Self {
-        Point {
-            r#type: "Point".to_owned(),
-            coordinates: [latitude, longitude],
-        }
-    }
-}
-
-pub async fn get_collection_client() -> azure_core::Result<CollectionClient> {
-    // CosmosDB configuration
-    let primary_key =
-        std::env::var("COSMOS_PRIMARY_KEY").expect("Set env variable COSMOS_PRIMARY_KEY first!");
-    let account = std::env::var("COSMOS_ACCOUNT").expect("Set env variable COSMOS_ACCOUNT first!");
-
-    let main_db = std::env::var("COSMOS_DB").expect("Specify the main DB name first!");
-    let users_collection =
-        std::env::var("USERS_TABLE").expect("Specify the name of the users collection!");
-
-    let authorization_token = match AuthorizationToken::primary_from_base64(&primary_key) {
+pub async fn get_collection_client(
+    cosmos_config: &CosmosConfig,
+    collection_name: &str,
+) -> azure_core::Result<CollectionClient> {
+    let authorization_token = match AuthorizationToken::primary_from_base64(&cosmos_config.primary_key) {
         Ok(token) => token,
         Err(err) => panic!("Error while fetching auth token for Cosmos DB: {:?}", err)
     };
@@ -128,116 +195,97 @@ pub async fn get_collection_client() -> azure_core::Result<CollectionClient> {
         port: 8081,
     })
     .build(); */
-    let client = CosmosClient::new(account, authorization_token);
+    let client = CosmosClient::new(cosmos_config.account.clone(), authorization_token);
 
     println!("Client built");
 
-    let database_client = client.database_client(main_db);
+    let database_client = client.database_client(cosmos_config.database.clone());
 
-    let collection_client = database_client.collection_client(users_collection.clone());
+    let collection_client = database_client.collection_client(collection_name.to_owned());
 
     Ok(collection_client)
 }
 
-pub async fn get_user_document(
-    token: &str,
-    collection: &CollectionClient,
-) -> Result<UserDocument, AuthError> {
-    println!("Querying user doc with token: {:?}", token);
-    let mut docs_stream = collection
-        .query_documents(Query::with_params(
-            "SELECT * FROM users AS u WHERE u.access_token = @token".to_owned(),
-            vec![Param::new("@token".into(), token)],
-        ))
-        .query_cross_partition(true) //TODO deep dive and figure out how to do in partition queries for this case
-        .max_item_count(1)
-        .into_stream::<UserDocument>();
-    if let Some(query_response) = docs_stream.next().await {
-        println!("User document found, {:?}", query_response);
-        // In this page, the documents are under results
-        if let Err(_) = query_response {
-            return Err(AuthError);
-        }
-        let query_response = query_response.unwrap();
-        if query_response.item_count > 0 {
-            let (user_document, _) = query_response.results.first().unwrap();
-            return Ok(user_document.clone());
-        }
-        println!("Empty doc array");
-        return Err(AuthError);
+impl UserDocument {
+    /// Whether either user blocked the other.
+    pub fn is_blocked_with(&self, other: &UserDocument) -> bool {
+        self.blocked_user_ids.contains(&other.id) || other.blocked_user_ids.contains(&self.id)
     }
-    Err(AuthError)
-}
 
-pub async fn get_user_document_by_id(
-    id: &str,
-    collection: &CollectionClient,
-) -> Result<UserDocument, AppError> {
-    println!("Querying user doc with id: {:?}", id);
-    let doc_client = collection
-        .document_client(id.clone(), &id)
-        .map_err(|_| AuthError)?
-        .get_document::<UserDocument>()
-        .await;
-
-    let result = doc_client.map_err(|_| AuthError).map(|doc| {
-        if let GetDocumentResponse::Found(document) = doc {
-            return Ok(document.document.document);
-        } else {
-            return Err(NotFoundError);
-        };
-    })?;
-    println!("Result: {:?}", result);
-    result
+    pub fn session_by_access_token_hash(&self, token_hash: &str) -> Option<&Session> {
+        self.sessions
+            .iter()
+            .find(|session| session.access_token_hash == token_hash)
+    }
 }
 
-pub async fn get_user_document_by_email(
-    email: &str,
-    collection: &CollectionClient,
-) -> Result<UserDocument, AuthError> {
-    println!("Querying user doc with email: {:?}", email);
-    let mut query_response = collection
-        .query_documents(Query::with_params(
-            "SELECT * FROM users AS u WHERE u.email = @email".to_owned(),
-            vec![Param::new("@email".into(), email)],
-        ))
-        .query_cross_partition(true) //TODO deep dive and figure out how to do in partition queries for this case
-        .max_item_count(1)
-        .into_stream::<UserDocument>();
-
-    if let Some(query_response) = query_response.next().await {
-        println!("User document found, {:?}", query_response);
-        // In this page, the documents are under results
-        if let Err(_) = query_response {
-            return Err(AuthError);
-        }
-        let query_response = query_response.unwrap();
-        if query_response.item_count > 0 {
-            let (user_document, _) = query_response.results.first().unwrap();
-            return Ok(user_document.clone());
-        }
-        println!("Empty doc array");
-        return Err(AuthError);
-    }
-    Err(AuthError)
+/// Resolves the user owning the access token sent with a request.
+/// Tokens of expired or revoked sessions are rejected.
+pub async fn get_user_document(token: &str, state: &AppState) -> Result<UserDocument, AuthError> {
+    let token_hash = session::hash_token(&state.config.token_hash_secret, token);
+    let user_document = state.user_store.get_by_token_hash(&token_hash).await?;
+    user_document
+        .session_by_access_token_hash(&token_hash)
+        .ok_or(AuthError::InvalidToken)?
+        .check_access()?;
+    Ok(user_document)
 }
 
 #[derive(Debug)]
-pub struct AuthError;
+pub enum AuthError {
+    /// The token is unknown, or the identity could not be verified.
+    InvalidToken,
+    ExpiredToken,
+    RevokedToken,
+}
 
 #[derive(Debug)]
 pub enum AppError {
     GenericError,
     AuthError,
+    ExpiredToken,
+    RevokedToken,
     NotFoundError,
     MissingLocationData,
+    /// The resource was modified concurrently, the operation can be retried.
+    Conflict,
+    IllegalTransition,
+    /// A match request without a valid recommendation ticket for the target user.
+    InvalidTicket,
+    InvalidCoordinates,
+    /// A file or request body of points of interest that couldn't be read.
+    InvalidPoiData,
+    /// A change not allowed on a meeting in its current status, or by this user.
+    IllegalMeetingAction,
+    /// Time slots of a meeting missing, in the past or too long.
+    InvalidTimeSlots,
+    /// A malformed or out of range parameter in the request, e.g. a search cursor.
+    BadRequest,
 }
 
 /// This makes it possible to use `?` to automatically convert a `AuthError`
 /// into an `AppError`.
 impl From<AuthError> for AppError {
     fn from(inner: AuthError) -> Self {
-        AppError::AuthError
+        match inner {
+            AuthError::InvalidToken => AppError::AuthError,
+            AuthError::ExpiredToken => AppError::ExpiredToken,
+            AuthError::RevokedToken => AppError::RevokedToken,
+        }
+    }
+}
+
+impl From<IllegalTransition> for AppError {
+    fn from(inner: IllegalTransition) -> Self {
+        println!("Illegal match transition: {:?} on a {:?} match", inner.action, inner.from);
+        AppError::IllegalTransition
+    }
+}
+
+impl From<InvalidCoordinates> for AppError {
+    fn from(inner: InvalidCoordinates) -> Self {
+        println!("Invalid coordinates: {:?}", inner);
+        AppError::InvalidCoordinates
     }
 }
 
@@ -255,6 +303,20 @@ impl From<VarError> for AppError {
     }
 }
 
+impl From<rusqlite::Error> for AppError {
+    fn from(inner: rusqlite::Error) -> Self {
+        println!("SQLite error: {:?}", inner);
+        AppError::GenericError
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(inner: serde_json::Error) -> Self {
+        println!("Serialization error: {:?}", inner);
+        AppError::GenericError
+    }
+}
+
 impl From<reqwest::Error> for AppError {
     fn from(inner: reqwest::Error) -> Self {
         println!("Reqwest error: {:?}", inner);
@@ -267,8 +329,18 @@ impl IntoResponse for AppError {
         let (status, error_message, code) = match self {
             AppError::GenericError => (StatusCode::INTERNAL_SERVER_ERROR, "Unknown error", 0),
             AppError::AuthError => (StatusCode::UNAUTHORIZED, "Authentication error", 0),
+            AppError::ExpiredToken => (StatusCode::UNAUTHORIZED, "Token expired", 2),
+            AppError::RevokedToken => (StatusCode::UNAUTHORIZED, "Token revoked", 3),
             AppError::NotFoundError => (StatusCode::NOT_FOUND, "Resource not found", 0),
             AppError::MissingLocationData => (StatusCode::NOT_FOUND, "Missing location data", 1),
+            AppError::Conflict => (StatusCode::CONFLICT, "Concurrent modification, try again", 4),
+            AppError::IllegalTransition => (StatusCode::CONFLICT, "Action not allowed on this match", 5),
+            AppError::InvalidTicket => (StatusCode::FORBIDDEN, "User wasn't recommended or the recommendation expired", 6),
+            AppError::InvalidCoordinates => (StatusCode::BAD_REQUEST, "Invalid coordinates", 7),
+            AppError::InvalidPoiData => (StatusCode::BAD_REQUEST, "Invalid points of interest", 8),
+            AppError::IllegalMeetingAction => (StatusCode::CONFLICT, "Action not allowed on this meeting", 9),
+            AppError::InvalidTimeSlots => (StatusCode::BAD_REQUEST, "Invalid time slots", 10),
+            AppError::BadRequest => (StatusCode::BAD_REQUEST, "Invalid request parameters", 11),
         };
 
         let body = Json(json!({
@@ -280,111 +352,6 @@ impl IntoResponse for AppError {
     }
 }
 
-/// Function used to index data in a cognitive search index.
-/// Currently needed since the azure rust sdk lacks any data operation on the cognitive search index.
-pub async fn index_documents(
-    endpoint: &str,
-    index_name: &str,
-    admin_key: &str,
-    index_actions: &[IndexAction],
-) -> Result<(), reqwest::Error> {
-    let mut map = HashMap::new();
-    map.insert("value", index_actions);
-
-    let client = reqwest::Client::new();
-    let res = client
-        .post(format!(
-            "{endpoint}/indexes('{index_name}')/docs/search.index"
-        ))
-        .query(&[("api-version", "2023-10-01-Preview")])
-        .header("api-key", admin_key)
-        .json(&map)
-        .send()
-        .await?;
-
-    Ok(())
-}
-
-#[derive(Serialize, Deserialize)]
-struct VectorQuery {
-    kind: String,
-    vector: Vec<f64>,
-    fields: String,
-    k: u32,
-}
-
-#[derive(Serialize, Deserialize)]
-struct CognitiveQueryBody {
-    select: String,
-    filter: String,
-    vectorFilterMode: String,
-    vectorQueries: Vec<VectorQuery>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct CognitiveResponse {
-    #[serde(rename = "@odata.context")]
-    context: String,
-    value: Vec<CognitiveResponseValue>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct CognitiveResponseValue {
-    #[serde(rename = "@search.score")]
-    search_score: f32,
-    id: String,
-    name: String,
-    description: String,
-}
-
-pub async fn cognitive_query(
-    endpoint: &str,
-    index_name: &str,
-    admin_key: &str,
-    user_document: &UserSearchData,
-) -> Result<CognitiveResponse, AppError> {
-    let user_location = user_document
-        .location
-        .as_ref()
-        .ok_or(AppError::MissingLocationData)?;
-    let user_description_embeddings = user_document
-        .description_embeddings
-        .as_ref()
-        .ok_or(AppError::NotFoundError)?;
-
-    let cognitive_query_body = CognitiveQueryBody {
-        select: "id, name, description".to_owned(),
-        filter: format!("id ne '{}' and geo.distance(location, geography'POINT({} {})') le 5",
-                        user_document.id,
-                        user_location.coordinates[0],
-                        user_location.coordinates[1]
-        ),
-        vectorFilterMode: "preFilter".to_owned(),
-        vectorQueries: vec![VectorQuery {
-            kind: "vector".to_owned(),
-            vector: user_description_embeddings.to_vec(),
-            fields: "description_embeddings".to_owned(),
-            k: 3,
-        }],
-    };
-
-    let client = reqwest::Client::new();
-    let response = client
-        .post(format!(
-            "{endpoint}/indexes('{index_name}')/docs/search.post.search"
-        ))
-        .query(&[("api-version", "2023-10-01-Preview")])
-        .header("api-key", admin_key)
-        .json(&cognitive_query_body)
-        .send()
-        .await?;
-
-    match response.status() {
-        StatusCode::OK => Ok(response.json::<CognitiveResponse>().await?),
-        _ => Err(AppError::GenericError),
-    }
-}
-
 /// Struct info: https://learn.microsoft.com/en-us/rest/api/searchservice/2023-10-01-preview/documents/?tabs=HTTP#indexaction
 #[derive(Serialize, Deserialize, Debug)]
 pub struct IndexAction {
@@ -402,3 +369,103 @@ pub enum IndexActionType {
     MergeOrUpload,
     Upload,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUSES: [MatchStatus; 5] = [
+        MatchStatus::Pending,
+        MatchStatus::AwaitingUserAction,
+        MatchStatus::Accepted,
+        MatchStatus::Denied,
+        MatchStatus::Cancelled,
+    ];
+    const ACTIONS: [MatchAction; 3] = [MatchAction::Accept, MatchAction::Reject, MatchAction::Cancel];
+
+    /// Every status and action, with the resulting status when the action is allowed.
+    const TRANSITIONS: [(MatchStatus, MatchAction, Option<MatchStatus>); 15] = [
+        (MatchStatus::Pending, MatchAction::Accept, None),
+        (MatchStatus::Pending, MatchAction::Reject, None),
+        (MatchStatus::Pending, MatchAction::Cancel, Some(MatchStatus::Cancelled)),
+        (MatchStatus::AwaitingUserAction, MatchAction::Accept, Some(MatchStatus::Accepted)),
+        (MatchStatus::AwaitingUserAction, MatchAction::Reject, Some(MatchStatus::Denied)),
+        (MatchStatus::AwaitingUserAction, MatchAction::Cancel, None),
+        (MatchStatus::Accepted, MatchAction::Accept, None),
+        (MatchStatus::Accepted, MatchAction::Reject, None),
+        (MatchStatus::Accepted, MatchAction::Cancel, Some(MatchStatus::Cancelled)),
+        (MatchStatus::Denied, MatchAction::Accept, None),
+        (MatchStatus::Denied, MatchAction::Reject, None),
+        (MatchStatus::Denied, MatchAction::Cancel, None),
+        (MatchStatus::Cancelled, MatchAction::Accept, None),
+        (MatchStatus::Cancelled, MatchAction::Reject, None),
+        (MatchStatus::Cancelled, MatchAction::Cancel, None),
+    ];
+
+    #[test]
+    fn transition_table_covers_every_status_and_action() {
+        for status in &STATUSES {
+            for action in ACTIONS {
+                let entries = TRANSITIONS
+                    .iter()
+                    .filter(|(from, table_action, _)| from == status && *table_action == action)
+                    .count();
+                assert_eq!(entries, 1, "{:?} {:?}", status, action);
+            }
+        }
+    }
+
+    #[test]
+    fn transitions_follow_the_table() {
+        for (from, action, expected) in &TRANSITIONS {
+            match (from.transition(*action), expected) {
+                (Ok(to), Some(expected)) => assert_eq!(&to, expected, "{:?} {:?}", from, action),
+                (Err(err), None) => {
+                    assert_eq!(&err.from, from);
+                    assert_eq!(err.action, *action);
+                }
+                (result, _) => panic!("{:?} {:?} gave {:?}, expected {:?}", from, action, result, expected),
+            }
+        }
+    }
+
+    #[test]
+    fn mirroring_swaps_the_sides_of_a_request() {
+        let expected = [
+            (MatchStatus::Pending, MatchStatus::AwaitingUserAction),
+            (MatchStatus::AwaitingUserAction, MatchStatus::Pending),
+            (MatchStatus::Accepted, MatchStatus::Accepted),
+            (MatchStatus::Denied, MatchStatus::Denied),
+            (MatchStatus::Cancelled, MatchStatus::Cancelled),
+        ];
+        for (status, mirrored) in expected {
+            assert_eq!(status.mirrored(), mirrored);
+            assert_eq!(status.mirrored().mirrored(), status);
+        }
+    }
+
+    #[test]
+    fn only_one_side_can_act_on_a_request() {
+        // Whatever one user may do to a pending request, the other one may not do the same
+        for status in [MatchStatus::Pending, MatchStatus::AwaitingUserAction] {
+            for action in ACTIONS {
+                assert!(
+                    status.transition(action).is_err() || status.mirrored().transition(action).is_err(),
+                    "{:?} {:?}",
+                    status,
+                    action
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn both_sides_see_the_same_outcome() {
+        // Statuses reached by a transition look the same from both sides
+        for (from, action, _) in &TRANSITIONS {
+            if let Ok(to) = from.transition(*action) {
+                assert_eq!(to.mirrored(), to, "{:?} {:?}", from, action);
+            }
+        }
+    }
+}
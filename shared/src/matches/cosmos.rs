@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use azure_core::StatusCode;
+use azure_data_cosmos::prelude::{CollectionClient, GetDocumentResponse, IfMatchCondition, Param, Query};
+use futures::StreamExt;
+
+use crate::{AppError, Versioned};
+
+use super::{into_page, parse_cursor, MatchPage, MatchRecord, MatchStore};
+
+/// Match store backed by a Cosmos DB collection, partitioned by pair id.
+pub struct CosmosMatchStore {
+    collection_client: CollectionClient,
+}
+
+impl CosmosMatchStore {
+    pub fn new(collection_client: CollectionClient) -> Self {
+        CosmosMatchStore { collection_client }
+    }
+}
+
+fn has_status(err: &azure_core::Error, status: StatusCode) -> bool {
+    err.as_http_error()
+        .is_some_and(|err| err.status() == status)
+}
+
+#[async_trait]
+impl MatchStore for CosmosMatchStore {
+    async fn get(
+        &self,
+        user_id: &str,
+        other_id: &str,
+    ) -> Result<Option<Versioned<MatchRecord>>, AppError> {
+        let id = MatchRecord::pair_id(user_id, other_id);
+        let response = self
+            .collection_client
+            .document_client(id.clone(), &id)?
+            .get_document::<MatchRecord>()
+            .await?;
+
+        match response {
+            GetDocumentResponse::Found(document) => Ok(Some(Versioned {
+                etag: document.document.document_attributes.etag().to_owned(),
+                document: document.document.document,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    async fn create(&self, record: &MatchRecord) -> Result<(), AppError> {
+        match self.collection_client.create_document(record.clone()).await {
+            Ok(_) => Ok(()),
+            Err(err) if has_status(&err, StatusCode::Conflict) => Err(AppError::Conflict),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn replace_if_match(&self, record: &MatchRecord, etag: &str) -> Result<(), AppError> {
+        let result = self
+            .collection_client
+            .document_client(record.id.clone(), &record.id)?
+            .replace_document(record.clone())
+            .if_match_condition(IfMatchCondition::Match(etag.to_owned()))
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(err) if has_status(&err, StatusCode::PreconditionFailed) => {
+                println!("Match {} was modified concurrently", record.id);
+                Err(AppError::Conflict)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn list_for_user(
+        &self,
+        user_id: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<MatchPage, AppError> {
+        let offset = parse_cursor(cursor)?;
+        // Most recently updated first like the other stores, the id keeping the order of ties stable
+        // across pages. Needs the composite index on (updated_at DESC, id ASC) set up by setup_azure.sh.
+        let mut docs_stream = self
+            .collection_client
+            .query_documents(Query::with_params(
+                "SELECT * FROM matches AS m WHERE ARRAY_CONTAINS(m.user_ids, @user_id)
+                 ORDER BY m.updated_at DESC, m.id ASC OFFSET @offset LIMIT @limit"
+                    .to_owned(),
+                vec![
+                    Param::new("@user_id".into(), user_id),
+                    Param::new("@offset".into(), offset),
+                    Param::new("@limit".into(), limit + 1),
+                ],
+            ))
+            .query_cross_partition(true)
+            .into_stream::<MatchRecord>();
+
+        let mut records = Vec::new();
+        while let Some(query_response) = docs_stream.next().await {
+            records.extend(query_response?.results.into_iter().map(|(record, _)| record));
+        }
+        Ok(into_page(records, offset, limit))
+    }
+}
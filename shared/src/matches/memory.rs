@@ -0,0 +1,74 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use async_trait::async_trait;
+
+use crate::{AppError, Versioned};
+
+use super::{into_page, parse_cursor, MatchPage, MatchRecord, MatchStore};
+
+/// Thread-safe match store keeping every match in memory along with its number of writes.
+#[derive(Default)]
+pub struct InMemoryMatchStore {
+    records: RwLock<HashMap<String, (MatchRecord, u64)>>,
+}
+
+#[async_trait]
+impl MatchStore for InMemoryMatchStore {
+    async fn get(
+        &self,
+        user_id: &str,
+        other_id: &str,
+    ) -> Result<Option<Versioned<MatchRecord>>, AppError> {
+        Ok(self
+            .records
+            .read()
+            .unwrap()
+            .get(&MatchRecord::pair_id(user_id, other_id))
+            .map(|(record, version)| Versioned {
+                document: record.clone(),
+                etag: version.to_string(),
+            }))
+    }
+
+    async fn create(&self, record: &MatchRecord) -> Result<(), AppError> {
+        let mut records = self.records.write().unwrap();
+        if records.contains_key(&record.id) {
+            return Err(AppError::Conflict);
+        }
+        records.insert(record.id.clone(), (record.clone(), 0));
+        Ok(())
+    }
+
+    async fn replace_if_match(&self, record: &MatchRecord, etag: &str) -> Result<(), AppError> {
+        let mut records = self.records.write().unwrap();
+        let (existing, version) = records.get_mut(&record.id).ok_or(AppError::NotFoundError)?;
+        if version.to_string() != etag {
+            return Err(AppError::Conflict);
+        }
+        *existing = record.clone();
+        *version += 1;
+        Ok(())
+    }
+
+    async fn list_for_user(
+        &self,
+        user_id: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<MatchPage, AppError> {
+        let offset = parse_cursor(cursor)?;
+        let mut records: Vec<MatchRecord> = self
+            .records
+            .read()
+            .unwrap()
+            .values()
+            .map(|(record, _)| record)
+            .filter(|record| record.user_ids.iter().any(|id| id == user_id))
+            .cloned()
+            .collect();
+        records.sort_by(|a, b| b.updated_at.cmp(&a.updated_at).then_with(|| a.id.cmp(&b.id)));
+
+        let records = records.into_iter().skip(offset).take(limit + 1).collect();
+        Ok(into_page(records, offset, limit))
+    }
+}
@@ -0,0 +1,183 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::UserStoreConfig, AppError, MatchStatus, Versioned};
+
+mod cosmos;
+mod memory;
+mod sqlite;
+
+pub use cosmos::CosmosMatchStore;
+pub use memory::InMemoryMatchStore;
+pub use sqlite::SqliteMatchStore;
+
+/// Number of matches returned per page when the client doesn't ask for a specific size.
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+pub const MAX_PAGE_SIZE: usize = 100;
+
+/// The relationship between two users, stored once for both of them.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MatchRecord {
+    /// Both user ids in order, see `MatchRecord::pair_id`.
+    pub id: String,
+    /// The two users, smallest id first.
+    pub user_ids: [String; 2],
+    /// Status as seen by the first user, the second one sees it mirrored.
+    pub status: MatchStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl azure_data_cosmos::CosmosEntity for MatchRecord {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.id.clone()
+    }
+}
+
+impl MatchRecord {
+    /// Identifier of the match between two users, the same whichever user it's computed for.
+    pub fn pair_id(user_id: &str, other_id: &str) -> String {
+        if user_id < other_id {
+            format!("{}:{}", user_id, other_id)
+        } else {
+            format!("{}:{}", other_id, user_id)
+        }
+    }
+
+    /// Starts a match between two users, with the status as seen by `user_id`.
+    pub fn new(user_id: &str, other_id: &str, status: MatchStatus) -> Self {
+        let now = Utc::now();
+        let (user_ids, status) = if user_id < other_id {
+            ([user_id.to_owned(), other_id.to_owned()], status)
+        } else {
+            ([other_id.to_owned(), user_id.to_owned()], status.mirrored())
+        };
+        MatchRecord {
+            id: MatchRecord::pair_id(user_id, other_id),
+            user_ids,
+            status,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn other_user(&self, user_id: &str) -> &str {
+        if self.user_ids[0] == user_id {
+            &self.user_ids[1]
+        } else {
+            &self.user_ids[0]
+        }
+    }
+
+    /// Status of the match as seen by the given user.
+    pub fn status_for(&self, user_id: &str) -> MatchStatus {
+        if self.user_ids[0] == user_id {
+            self.status.clone()
+        } else {
+            self.status.mirrored()
+        }
+    }
+
+    /// Changes the status of the match, given as seen by `user_id`.
+    pub fn set_status_for(&mut self, user_id: &str, status: MatchStatus) {
+        self.status = if self.user_ids[0] == user_id {
+            status
+        } else {
+            status.mirrored()
+        };
+        self.updated_at = Utc::now();
+    }
+}
+
+/// A page of the matches of a user.
+#[derive(Debug)]
+pub struct MatchPage {
+    pub records: Vec<MatchRecord>,
+    /// Cursor of the next page, if there's one.
+    pub next_cursor: Option<String>,
+}
+
+/// Persistence layer for matches, keyed by the pair of users.
+#[async_trait]
+pub trait MatchStore: Send + Sync {
+    /// Fetches the match between two users, along with its version.
+    async fn get(&self, user_id: &str, other_id: &str)
+        -> Result<Option<Versioned<MatchRecord>>, AppError>;
+
+    /// Creates a match, failing with `AppError::Conflict` if the pair already has one.
+    async fn create(&self, record: &MatchRecord) -> Result<(), AppError>;
+
+    /// Overwrites a match only if it's still at the version it was read at, failing with
+    /// `AppError::Conflict` if it was modified in the meantime.
+    async fn replace_if_match(&self, record: &MatchRecord, etag: &str) -> Result<(), AppError>;
+
+    /// Lists the matches of a user, starting from the given cursor (the first page if missing).
+    async fn list_for_user(
+        &self,
+        user_id: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<MatchPage, AppError>;
+}
+
+/// Every match of a user, going through all the pages.
+pub async fn list_all_for_user(
+    match_store: &dyn MatchStore,
+    user_id: &str,
+) -> Result<Vec<MatchRecord>, AppError> {
+    let mut records = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = match_store
+            .list_for_user(user_id, cursor.as_deref(), MAX_PAGE_SIZE)
+            .await?;
+        records.extend(page.records);
+        match page.next_cursor {
+            Some(next_cursor) => cursor = Some(next_cursor),
+            None => return Ok(records),
+        }
+    }
+}
+
+/// Cursors are offsets in the list of matches, opaque to clients.
+fn parse_cursor(cursor: Option<&str>) -> Result<usize, AppError> {
+    cursor
+        .map(|cursor| {
+            cursor.parse().map_err(|_| {
+                println!("Invalid matches cursor: {}", cursor);
+                AppError::BadRequest
+            })
+        })
+        .transpose()
+        .map(|offset| offset.unwrap_or(0))
+}
+
+/// Builds the page from records fetched with one more than `limit`, to know if a next page exists.
+fn into_page(mut records: Vec<MatchRecord>, offset: usize, limit: usize) -> MatchPage {
+    let next_cursor = (records.len() > limit).then(|| (offset + limit).to_string());
+    records.truncate(limit);
+    MatchPage {
+        records,
+        next_cursor,
+    }
+}
+
+/// Builds the match store of the configured user store backend.
+pub async fn get_match_store(user_store_config: &UserStoreConfig) -> Arc<dyn MatchStore> {
+    match user_store_config {
+        UserStoreConfig::Cosmos(cosmos_config) => Arc::new(CosmosMatchStore::new(
+            crate::get_collection_client(cosmos_config, &cosmos_config.matches_collection)
+                .await
+                .unwrap(),
+        )),
+        UserStoreConfig::Sqlite { path } => {
+            Arc::new(SqliteMatchStore::open(path).expect("Could not open the SQLite database"))
+        }
+        UserStoreConfig::Memory => Arc::new(InMemoryMatchStore::default()),
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::{
+    store::sqlite::{match_status_from_sql, open_connection, run_blocking},
+    AppError, Versioned,
+};
+
+use super::{into_page, parse_cursor, MatchPage, MatchRecord, MatchStore};
+
+/// Match store backed by the same SQLite database as the user store.
+pub struct SqliteMatchStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteMatchStore {
+    pub fn open(path: &str) -> Result<Self, AppError> {
+        Ok(SqliteMatchStore {
+            connection: Arc::new(Mutex::new(open_connection(path)?)),
+        })
+    }
+}
+
+const RECORD_COLUMNS: &str = "id, first_user_id, second_user_id, status, created_at, updated_at";
+
+fn record_from_row(row: &Row) -> rusqlite::Result<MatchRecord> {
+    Ok(MatchRecord {
+        id: row.get(0)?,
+        user_ids: [row.get(1)?, row.get(2)?],
+        status: match_status_from_sql(row, 3)?,
+        created_at: row.get(4)?,
+        updated_at: row.get(5)?,
+    })
+}
+
+#[async_trait]
+impl MatchStore for SqliteMatchStore {
+    async fn get(
+        &self,
+        user_id: &str,
+        other_id: &str,
+    ) -> Result<Option<Versioned<MatchRecord>>, AppError> {
+        let id = MatchRecord::pair_id(user_id, other_id);
+        run_blocking(&self.connection, move |connection| {
+            Ok(connection
+                .query_row(
+                    &format!("SELECT {}, version FROM match_records WHERE id = ?1", RECORD_COLUMNS),
+                    [&id],
+                    |row| {
+                        Ok(Versioned {
+                            document: record_from_row(row)?,
+                            etag: row.get::<_, i64>(6)?.to_string(),
+                        })
+                    },
+                )
+                .optional()?)
+        })
+        .await
+    }
+
+    async fn create(&self, record: &MatchRecord) -> Result<(), AppError> {
+        let record = record.clone();
+        run_blocking(&self.connection, move |connection| {
+            let inserted = connection.execute(
+                "INSERT INTO match_records (id, first_user_id, second_user_id, status, created_at,
+                    updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (id) DO NOTHING",
+                params![
+                    record.id,
+                    record.user_ids[0],
+                    record.user_ids[1],
+                    record.status.clone() as i64,
+                    record.created_at,
+                    record.updated_at,
+                ],
+            )?;
+            match inserted {
+                0 => Err(AppError::Conflict),
+                _ => Ok(()),
+            }
+        })
+        .await
+    }
+
+    async fn replace_if_match(&self, record: &MatchRecord, etag: &str) -> Result<(), AppError> {
+        let record = record.clone();
+        let version: i64 = etag.parse().map_err(|_| AppError::Conflict)?;
+        run_blocking(&self.connection, move |connection| {
+            let updated = connection.execute(
+                "UPDATE match_records SET status = ?1, updated_at = ?2, version = version + 1
+                 WHERE id = ?3 AND version = ?4",
+                params![record.status.clone() as i64, record.updated_at, record.id, version],
+            )?;
+            if updated == 0 {
+                println!("Match {} was modified concurrently", record.id);
+                return Err(AppError::Conflict);
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list_for_user(
+        &self,
+        user_id: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<MatchPage, AppError> {
+        let offset = parse_cursor(cursor)?;
+        let user_id = user_id.to_owned();
+        let records = run_blocking(&self.connection, move |connection| {
+            Ok(connection
+                .prepare(&format!(
+                    "SELECT {} FROM match_records
+                     WHERE first_user_id = ?1 OR second_user_id = ?1
+                     ORDER BY updated_at DESC, id LIMIT ?2 OFFSET ?3",
+                    RECORD_COLUMNS
+                ))?
+                .query_map(params![user_id, limit as i64 + 1, offset as i64], record_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?)
+        })
+        .await?;
+        Ok(into_page(records, offset, limit))
+    }
+}
@@ -0,0 +1,87 @@
+use async_trait::async_trait;
+use azure_core::StatusCode;
+use azure_data_cosmos::prelude::{CollectionClient, GetDocumentResponse, IfMatchCondition, Param, Query};
+use futures::StreamExt;
+
+use crate::{AppError, Versioned};
+
+use super::{Meeting, MeetingStore};
+
+/// Meeting store backed by a Cosmos DB collection, partitioned by meeting id.
+pub struct CosmosMeetingStore {
+    collection_client: CollectionClient,
+}
+
+impl CosmosMeetingStore {
+    pub fn new(collection_client: CollectionClient) -> Self {
+        CosmosMeetingStore { collection_client }
+    }
+}
+
+#[async_trait]
+impl MeetingStore for CosmosMeetingStore {
+    async fn create(&self, meeting: &Meeting) -> Result<(), AppError> {
+        self.collection_client
+            .create_document(meeting.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Versioned<Meeting>, AppError> {
+        let response = self
+            .collection_client
+            .document_client(id, &id)?
+            .get_document::<Meeting>()
+            .await?;
+
+        match response {
+            GetDocumentResponse::Found(document) => Ok(Versioned {
+                etag: document.document.document_attributes.etag().to_owned(),
+                document: document.document.document,
+            }),
+            _ => Err(AppError::NotFoundError),
+        }
+    }
+
+    async fn replace_if_match(&self, meeting: &Meeting, etag: &str) -> Result<(), AppError> {
+        let result = self
+            .collection_client
+            .document_client(meeting.id.clone(), &meeting.id)?
+            .replace_document(meeting.clone())
+            .if_match_condition(IfMatchCondition::Match(etag.to_owned()))
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(err)
+                if err
+                    .as_http_error()
+                    .is_some_and(|err| err.status() == StatusCode::PreconditionFailed) =>
+            {
+                println!("Meeting {} was modified concurrently", meeting.id);
+                Err(AppError::Conflict)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn list_for_user(&self, user_id: &str, limit: usize) -> Result<Vec<Meeting>, AppError> {
+        // Cross-partition ORDER BY isn't supported by the SDK, so every meeting of the user is sorted here
+        let mut docs_stream = self
+            .collection_client
+            .query_documents(Query::with_params(
+                "SELECT * FROM meetings AS m WHERE ARRAY_CONTAINS(m.user_ids, @user_id)".to_owned(),
+                vec![Param::new("@user_id".into(), user_id)],
+            ))
+            .query_cross_partition(true)
+            .into_stream::<Meeting>();
+
+        let mut meetings = Vec::new();
+        while let Some(query_response) = docs_stream.next().await {
+            meetings.extend(query_response?.results.into_iter().map(|(meeting, _)| meeting));
+        }
+        meetings.sort_by_key(|meeting| std::cmp::Reverse(meeting.updated_at));
+        meetings.truncate(limit);
+        Ok(meetings)
+    }
+}
@@ -0,0 +1,63 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use async_trait::async_trait;
+
+use crate::{AppError, Versioned};
+
+use super::{Meeting, MeetingStore};
+
+/// Thread-safe meeting store keeping every meeting in memory along with its number of writes.
+#[derive(Default)]
+pub struct InMemoryMeetingStore {
+    meetings: RwLock<HashMap<String, (Meeting, u64)>>,
+}
+
+#[async_trait]
+impl MeetingStore for InMemoryMeetingStore {
+    async fn create(&self, meeting: &Meeting) -> Result<(), AppError> {
+        let mut meetings = self.meetings.write().unwrap();
+        if meetings.contains_key(&meeting.id) {
+            return Err(AppError::Conflict);
+        }
+        meetings.insert(meeting.id.clone(), (meeting.clone(), 0));
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Versioned<Meeting>, AppError> {
+        self.meetings
+            .read()
+            .unwrap()
+            .get(id)
+            .map(|(meeting, version)| Versioned {
+                document: meeting.clone(),
+                etag: version.to_string(),
+            })
+            .ok_or(AppError::NotFoundError)
+    }
+
+    async fn replace_if_match(&self, meeting: &Meeting, etag: &str) -> Result<(), AppError> {
+        let mut meetings = self.meetings.write().unwrap();
+        let (existing, version) = meetings.get_mut(&meeting.id).ok_or(AppError::NotFoundError)?;
+        if version.to_string() != etag {
+            return Err(AppError::Conflict);
+        }
+        *existing = meeting.clone();
+        *version += 1;
+        Ok(())
+    }
+
+    async fn list_for_user(&self, user_id: &str, limit: usize) -> Result<Vec<Meeting>, AppError> {
+        let mut meetings: Vec<Meeting> = self
+            .meetings
+            .read()
+            .unwrap()
+            .values()
+            .map(|(meeting, _)| meeting)
+            .filter(|meeting| meeting.has_participant(user_id))
+            .cloned()
+            .collect();
+        meetings.sort_by_key(|meeting| std::cmp::Reverse(meeting.updated_at));
+        meetings.truncate(limit);
+        Ok(meetings)
+    }
+}
@@ -0,0 +1,410 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::UserStoreConfig, session::generate_token, AppError, Poi, Versioned};
+
+mod cosmos;
+mod memory;
+mod sqlite;
+
+pub use cosmos::CosmosMeetingStore;
+pub use memory::InMemoryMeetingStore;
+pub use sqlite::SqliteMeetingStore;
+
+/// Time slots a single proposal can offer.
+pub const MAX_TIME_SLOTS: usize = 5;
+/// Longest a time slot can last, in hours.
+pub const MAX_SLOT_HOURS: i64 = 12;
+/// Farthest in the future a time slot can start, in days.
+pub const MAX_SLOT_DAYS_AHEAD: i64 = 365;
+/// Meetings returned when listing those of a user, most recently updated first.
+pub const MAX_LISTED_MEETINGS: usize = 50;
+
+/// A time range a meeting can take place in.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeSlot {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl TimeSlot {
+    /// Fails unless there are between 1 and [`MAX_TIME_SLOTS`] slots, each starting in the next
+    /// [`MAX_SLOT_DAYS_AHEAD`] days, before it ends, and lasting at most [`MAX_SLOT_HOURS`].
+    pub fn check_all(slots: &[TimeSlot]) -> Result<(), AppError> {
+        let now = Utc::now();
+        let horizon = now + Duration::days(MAX_SLOT_DAYS_AHEAD);
+        let valid = (1..=MAX_TIME_SLOTS).contains(&slots.len())
+            && slots.iter().all(|slot| {
+                slot.start > now
+                    && slot.start <= horizon
+                    && slot.start < slot.end
+                    && slot.end - slot.start <= Duration::hours(MAX_SLOT_HOURS)
+            });
+        if !valid {
+            println!("Invalid time slots: {:?}", slots);
+            return Err(AppError::InvalidTimeSlots);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MeetingStatus {
+    /// Waiting for an answer from `awaiting_user_id`.
+    Proposed,
+    /// A slot was accepted, see `confirmed_slot`.
+    Confirmed,
+    Declined,
+    Cancelled,
+}
+
+impl MeetingStatus {
+    /// Name stored in the database, the same as the serialized one.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MeetingStatus::Proposed => "proposed",
+            MeetingStatus::Confirmed => "confirmed",
+            MeetingStatus::Declined => "declined",
+            MeetingStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// A change requested by one of the users of a meeting.
+#[derive(Clone, Debug)]
+pub enum MeetingChange {
+    /// Accepts the slot at this index of the current proposal.
+    Accept(usize),
+    Decline,
+    /// Replaces the current proposal with another place and times, to be answered by the other user.
+    CounterPropose { poi: Poi, slots: Vec<TimeSlot> },
+    Cancel,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MeetingAction {
+    Propose,
+    Accept,
+    Decline,
+    CounterPropose,
+    Cancel,
+}
+
+/// An entry of the history of a meeting.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MeetingEvent {
+    pub user_id: String,
+    pub action: MeetingAction,
+    /// Status of the meeting after the action.
+    pub status: MeetingStatus,
+    /// Place proposed, for proposals and counter-proposals.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poi_id: Option<String>,
+    /// Slots proposed, or the one accepted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub slots: Vec<TimeSlot>,
+    pub at: DateTime<Utc>,
+}
+
+/// A meeting between two users with an accepted match, negotiated through proposals.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Meeting {
+    pub id: String,
+    /// The user who made the first proposal, then the other one.
+    pub user_ids: [String; 2],
+    pub status: MeetingStatus,
+    /// Author of the current proposal.
+    pub proposed_by: String,
+    /// User expected to answer the current proposal, only while it's proposed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub awaiting_user_id: Option<String>,
+    pub poi: Poi,
+    pub slots: Vec<TimeSlot>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmed_slot: Option<TimeSlot>,
+    /// Every action taken on the meeting, oldest first.
+    pub history: Vec<MeetingEvent>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl azure_data_cosmos::CosmosEntity for Meeting {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.id.clone()
+    }
+}
+
+impl Meeting {
+    /// A proposal from `user_id` to `other_id`, with slots already checked.
+    pub fn propose(user_id: &str, other_id: &str, poi: Poi, slots: Vec<TimeSlot>) -> Self {
+        let now = Utc::now();
+        Meeting {
+            id: generate_token(24),
+            user_ids: [user_id.to_owned(), other_id.to_owned()],
+            status: MeetingStatus::Proposed,
+            proposed_by: user_id.to_owned(),
+            awaiting_user_id: Some(other_id.to_owned()),
+            history: vec![MeetingEvent {
+                user_id: user_id.to_owned(),
+                action: MeetingAction::Propose,
+                status: MeetingStatus::Proposed,
+                poi_id: Some(poi.id.clone()),
+                slots: slots.clone(),
+                at: now,
+            }],
+            poi,
+            slots,
+            confirmed_slot: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn has_participant(&self, user_id: &str) -> bool {
+        self.user_ids.iter().any(|id| id == user_id)
+    }
+
+    pub fn other_user(&self, user_id: &str) -> &str {
+        if self.user_ids[0] == user_id {
+            &self.user_ids[1]
+        } else {
+            &self.user_ids[0]
+        }
+    }
+
+    /// Whether the confirmed slot has ended. Proposals are never over, as they didn't take place.
+    pub fn is_over(&self) -> bool {
+        self.confirmed_slot.is_some_and(|slot| slot.end <= Utc::now())
+    }
+
+    /// Applies a change requested by `user_id`, a participant. Only the user a proposal is waiting
+    /// for can accept, decline or counter it, and either user can cancel a meeting until it's over.
+    /// Declined and cancelled meetings are final.
+    pub fn apply(&mut self, user_id: &str, change: MeetingChange) -> Result<(), AppError> {
+        let awaited = self.awaiting_user_id.as_deref() == Some(user_id);
+        let (action, event_poi_id, event_slots) = match (self.status, change) {
+            (MeetingStatus::Proposed, MeetingChange::Accept(index)) if awaited => {
+                let slot = *self.slots.get(index).ok_or(AppError::InvalidTimeSlots)?;
+                if slot.start <= Utc::now() {
+                    return Err(AppError::InvalidTimeSlots);
+                }
+                self.status = MeetingStatus::Confirmed;
+                self.confirmed_slot = Some(slot);
+                self.awaiting_user_id = None;
+                (MeetingAction::Accept, None, vec![slot])
+            }
+            (MeetingStatus::Proposed, MeetingChange::Decline) if awaited => {
+                self.status = MeetingStatus::Declined;
+                self.awaiting_user_id = None;
+                (MeetingAction::Decline, None, Vec::new())
+            }
+            (MeetingStatus::Proposed, MeetingChange::CounterPropose { poi, slots }) if awaited => {
+                TimeSlot::check_all(&slots)?;
+                let poi_id = poi.id.clone();
+                self.awaiting_user_id = Some(self.proposed_by.clone());
+                self.proposed_by = user_id.to_owned();
+                self.poi = poi;
+                self.slots = slots.clone();
+                (MeetingAction::CounterPropose, Some(poi_id), slots)
+            }
+            (MeetingStatus::Proposed | MeetingStatus::Confirmed, MeetingChange::Cancel) if !self.is_over() => {
+                self.status = MeetingStatus::Cancelled;
+                self.awaiting_user_id = None;
+                (MeetingAction::Cancel, None, Vec::new())
+            }
+            (status, change) => {
+                println!("Illegal meeting change: {:?} on a {:?} meeting by {}", change, status, user_id);
+                return Err(AppError::IllegalMeetingAction);
+            }
+        };
+
+        let now = Utc::now();
+        self.history.push(MeetingEvent {
+            user_id: user_id.to_owned(),
+            action,
+            status: self.status,
+            poi_id: event_poi_id,
+            slots: event_slots,
+            at: now,
+        });
+        self.updated_at = now;
+        Ok(())
+    }
+}
+
+/// Persistence layer for meetings.
+#[async_trait]
+pub trait MeetingStore: Send + Sync {
+    async fn create(&self, meeting: &Meeting) -> Result<(), AppError>;
+
+    /// Fetches a meeting, along with its version.
+    async fn get(&self, id: &str) -> Result<Versioned<Meeting>, AppError>;
+
+    /// Overwrites a meeting only if it's still at the version it was read at, failing with
+    /// `AppError::Conflict` if it was modified in the meantime.
+    async fn replace_if_match(&self, meeting: &Meeting, etag: &str) -> Result<(), AppError>;
+
+    /// The latest meetings of the user, most recently updated first.
+    async fn list_for_user(&self, user_id: &str, limit: usize) -> Result<Vec<Meeting>, AppError>;
+}
+
+/// Builds the meeting store of the configured user store backend.
+pub async fn get_meeting_store(user_store_config: &UserStoreConfig) -> Arc<dyn MeetingStore> {
+    match user_store_config {
+        UserStoreConfig::Cosmos(cosmos_config) => Arc::new(CosmosMeetingStore::new(
+            crate::get_collection_client(cosmos_config, &cosmos_config.meetings_collection)
+                .await
+                .unwrap(),
+        )),
+        UserStoreConfig::Sqlite { path } => {
+            Arc::new(SqliteMeetingStore::open(path).expect("Could not open the SQLite database"))
+        }
+        UserStoreConfig::Memory => Arc::new(InMemoryMeetingStore::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GeoPoint, PoiCategory};
+
+    use super::*;
+
+    fn slot(hours_from_now: i64) -> TimeSlot {
+        let start = Utc::now() + Duration::hours(hours_from_now);
+        TimeSlot {
+            start,
+            end: start + Duration::hours(1),
+        }
+    }
+
+    fn proposal() -> Meeting {
+        let poi = Poi {
+            id: "poi".to_owned(),
+            name: "Café".to_owned(),
+            address: None,
+            category: PoiCategory::default(),
+            opening_hours: None,
+            partner: false,
+            location: GeoPoint::new(45.4642, 9.19).unwrap(),
+            timezone: None,
+        };
+        Meeting::propose("alice", "bob", poi, vec![slot(24)])
+    }
+
+    fn counter_proposal() -> MeetingChange {
+        let mut poi = proposal().poi;
+        poi.id = "other_poi".to_owned();
+        MeetingChange::CounterPropose {
+            poi,
+            slots: vec![slot(48), slot(72)],
+        }
+    }
+
+    fn assert_illegal(meeting: &mut Meeting, user_id: &str, change: MeetingChange) {
+        let before = meeting.history.len();
+        let result = meeting.apply(user_id, change);
+        assert!(matches!(result, Err(AppError::IllegalMeetingAction)), "{:?}", result);
+        assert_eq!(meeting.history.len(), before);
+    }
+
+    #[test]
+    fn only_the_awaited_user_can_answer() {
+        let mut meeting = proposal();
+        assert_illegal(&mut meeting, "alice", MeetingChange::Accept(0));
+        assert_illegal(&mut meeting, "alice", MeetingChange::Decline);
+        assert_illegal(&mut meeting, "alice", counter_proposal());
+        assert_eq!(meeting.status, MeetingStatus::Proposed);
+        assert_eq!(meeting.awaiting_user_id.as_deref(), Some("bob"));
+
+        meeting.apply("bob", MeetingChange::Accept(0)).unwrap();
+        assert_eq!(meeting.status, MeetingStatus::Confirmed);
+        assert_eq!(meeting.confirmed_slot, Some(meeting.slots[0]));
+        assert_eq!(meeting.awaiting_user_id, None);
+
+        let mut meeting = proposal();
+        meeting.apply("bob", MeetingChange::Decline).unwrap();
+        assert_eq!(meeting.status, MeetingStatus::Declined);
+        assert_eq!(meeting.awaiting_user_id, None);
+    }
+
+    #[test]
+    fn counter_proposals_swap_who_proposes_and_who_answers() {
+        let mut meeting = proposal();
+        meeting.apply("bob", counter_proposal()).unwrap();
+        assert_eq!(meeting.status, MeetingStatus::Proposed);
+        assert_eq!(meeting.proposed_by, "bob");
+        assert_eq!(meeting.awaiting_user_id.as_deref(), Some("alice"));
+        assert_eq!(meeting.poi.id, "other_poi");
+        assert_eq!(meeting.slots.len(), 2);
+        assert_eq!(meeting.history.last().unwrap().action, MeetingAction::CounterPropose);
+
+        // Bob now waits for Alice, who can accept one of the new slots
+        assert_illegal(&mut meeting, "bob", MeetingChange::Accept(1));
+        meeting.apply("alice", MeetingChange::Accept(1)).unwrap();
+        assert_eq!(meeting.confirmed_slot, Some(meeting.slots[1]));
+    }
+
+    #[test]
+    fn slots_that_already_started_cannot_be_accepted() {
+        let mut meeting = proposal();
+        meeting.slots = vec![slot(-1), slot(24)];
+
+        let result = meeting.apply("bob", MeetingChange::Accept(0));
+        assert!(matches!(result, Err(AppError::InvalidTimeSlots)), "{:?}", result);
+        let result = meeting.apply("bob", MeetingChange::Accept(2));
+        assert!(matches!(result, Err(AppError::InvalidTimeSlots)), "{:?}", result);
+        assert_eq!(meeting.status, MeetingStatus::Proposed);
+        assert_eq!(meeting.confirmed_slot, None);
+
+        meeting.apply("bob", MeetingChange::Accept(1)).unwrap();
+        assert_eq!(meeting.status, MeetingStatus::Confirmed);
+    }
+
+    #[test]
+    fn declined_and_cancelled_meetings_are_final() {
+        let mut declined = proposal();
+        declined.apply("bob", MeetingChange::Decline).unwrap();
+        let mut cancelled = proposal();
+        cancelled.apply("alice", MeetingChange::Cancel).unwrap();
+
+        for (meeting, status) in [(&mut declined, MeetingStatus::Declined), (&mut cancelled, MeetingStatus::Cancelled)] {
+            for user_id in ["alice", "bob"] {
+                assert_illegal(meeting, user_id, MeetingChange::Accept(0));
+                assert_illegal(meeting, user_id, MeetingChange::Decline);
+                assert_illegal(meeting, user_id, counter_proposal());
+                assert_illegal(meeting, user_id, MeetingChange::Cancel);
+            }
+            assert_eq!(meeting.status, status);
+        }
+    }
+
+    #[test]
+    fn either_user_can_cancel_until_the_meeting_is_over() {
+        let mut meeting = proposal();
+        meeting.apply("alice", MeetingChange::Cancel).unwrap();
+        assert_eq!(meeting.status, MeetingStatus::Cancelled);
+
+        let mut meeting = proposal();
+        meeting.apply("bob", MeetingChange::Accept(0)).unwrap();
+        meeting.apply("bob", MeetingChange::Cancel).unwrap();
+        assert_eq!(meeting.status, MeetingStatus::Cancelled);
+    }
+
+    #[test]
+    fn meetings_that_took_place_cannot_be_cancelled() {
+        let mut meeting = proposal();
+        meeting.apply("bob", MeetingChange::Accept(0)).unwrap();
+        meeting.confirmed_slot = Some(slot(-2));
+
+        let result = meeting.apply("alice", MeetingChange::Cancel);
+        assert!(matches!(result, Err(AppError::IllegalMeetingAction)), "{:?}", result);
+        assert_eq!(meeting.status, MeetingStatus::Confirmed);
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{
+    store::sqlite::{open_connection, run_blocking},
+    AppError, Versioned,
+};
+
+use super::{Meeting, MeetingStore};
+
+/// Meeting store backed by the same SQLite database as the user store. Meetings are only ever looked
+/// up by id or user, so they're stored as JSON next to the columns queried.
+pub struct SqliteMeetingStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteMeetingStore {
+    pub fn open(path: &str) -> Result<Self, AppError> {
+        Ok(SqliteMeetingStore {
+            connection: Arc::new(Mutex::new(open_connection(path)?)),
+        })
+    }
+}
+
+#[async_trait]
+impl MeetingStore for SqliteMeetingStore {
+    async fn create(&self, meeting: &Meeting) -> Result<(), AppError> {
+        let meeting = meeting.clone();
+        let document = serde_json::to_string(&meeting)?;
+        run_blocking(&self.connection, move |connection| {
+            let inserted = connection.execute(
+                "INSERT INTO meetings (id, first_user_id, second_user_id, status, document, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (id) DO NOTHING",
+                params![
+                    meeting.id,
+                    meeting.user_ids[0],
+                    meeting.user_ids[1],
+                    meeting.status.as_str(),
+                    document,
+                    meeting.updated_at,
+                ],
+            )?;
+            match inserted {
+                0 => Err(AppError::Conflict),
+                _ => Ok(()),
+            }
+        })
+        .await
+    }
+
+    async fn get(&self, id: &str) -> Result<Versioned<Meeting>, AppError> {
+        let id = id.to_owned();
+        run_blocking(&self.connection, move |connection| {
+            let (document, version) = connection
+                .query_row(
+                    "SELECT document, version FROM meetings WHERE id = ?1",
+                    [&id],
+                    |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+                )
+                .optional()?
+                .ok_or(AppError::NotFoundError)?;
+            Ok(Versioned {
+                document: serde_json::from_str(&document)?,
+                etag: version.to_string(),
+            })
+        })
+        .await
+    }
+
+    async fn replace_if_match(&self, meeting: &Meeting, etag: &str) -> Result<(), AppError> {
+        let meeting = meeting.clone();
+        let document = serde_json::to_string(&meeting)?;
+        let version: i64 = etag.parse().map_err(|_| AppError::Conflict)?;
+        run_blocking(&self.connection, move |connection| {
+            let updated = connection.execute(
+                "UPDATE meetings SET status = ?1, document = ?2, updated_at = ?3, version = version + 1
+                 WHERE id = ?4 AND version = ?5",
+                params![meeting.status.as_str(), document, meeting.updated_at, meeting.id, version],
+            )?;
+            if updated == 0 {
+                println!("Meeting {} was modified concurrently", meeting.id);
+                return Err(AppError::Conflict);
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list_for_user(&self, user_id: &str, limit: usize) -> Result<Vec<Meeting>, AppError> {
+        let user_id = user_id.to_owned();
+        run_blocking(&self.connection, move |connection| {
+            let documents = connection
+                .prepare(
+                    "SELECT document FROM meetings
+                     WHERE first_user_id = ?1 OR second_user_id = ?1
+                     ORDER BY updated_at DESC, id LIMIT ?2",
+                )?
+                .query_map(params![user_id, limit as i64], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            documents
+                .iter()
+                .map(|document| Ok(serde_json::from_str(document)?))
+                .collect()
+        })
+        .await
+    }
+}
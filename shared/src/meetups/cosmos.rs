@@ -0,0 +1,61 @@
+use async_trait::async_trait;
+use azure_data_cosmos::prelude::{CollectionClient, GetDocumentResponse, Param, Query};
+use futures::StreamExt;
+
+use crate::AppError;
+
+use super::{Meetup, MeetupStore};
+
+/// Meetup store backed by a Cosmos DB collection, partitioned by meetup id.
+pub struct CosmosMeetupStore {
+    collection_client: CollectionClient,
+}
+
+impl CosmosMeetupStore {
+    pub fn new(collection_client: CollectionClient) -> Self {
+        CosmosMeetupStore { collection_client }
+    }
+}
+
+#[async_trait]
+impl MeetupStore for CosmosMeetupStore {
+    async fn create(&self, meetup: &Meetup) -> Result<(), AppError> {
+        self.collection_client
+            .create_document(meetup.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Meetup, AppError> {
+        let response = self
+            .collection_client
+            .document_client(id, &id)?
+            .get_document::<Meetup>()
+            .await?;
+
+        match response {
+            GetDocumentResponse::Found(document) => Ok(document.document.document),
+            _ => Err(AppError::NotFoundError),
+        }
+    }
+
+    async fn list_for_user(&self, user_id: &str, limit: usize) -> Result<Vec<Meetup>, AppError> {
+        // Cross-partition ORDER BY isn't supported by the SDK, so every meetup of the user is sorted here
+        let mut docs_stream = self
+            .collection_client
+            .query_documents(Query::with_params(
+                "SELECT * FROM meetups AS m WHERE ARRAY_CONTAINS(m.participant_ids, @user_id)".to_owned(),
+                vec![Param::new("@user_id".into(), user_id)],
+            ))
+            .query_cross_partition(true)
+            .into_stream::<Meetup>();
+
+        let mut meetups = Vec::new();
+        while let Some(query_response) = docs_stream.next().await {
+            meetups.extend(query_response?.results.into_iter().map(|(meetup, _)| meetup));
+        }
+        meetups.sort_by_key(|meetup| std::cmp::Reverse(meetup.created_at));
+        meetups.truncate(limit);
+        Ok(meetups)
+    }
+}
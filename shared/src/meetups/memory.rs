@@ -0,0 +1,47 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use async_trait::async_trait;
+
+use crate::AppError;
+
+use super::{Meetup, MeetupStore};
+
+/// Meetup store keeping every meetup in memory, nothing is persisted.
+#[derive(Default)]
+pub struct InMemoryMeetupStore {
+    meetups: RwLock<HashMap<String, Meetup>>,
+}
+
+#[async_trait]
+impl MeetupStore for InMemoryMeetupStore {
+    async fn create(&self, meetup: &Meetup) -> Result<(), AppError> {
+        self.meetups
+            .write()
+            .unwrap()
+            .insert(meetup.id.clone(), meetup.clone());
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Meetup, AppError> {
+        self.meetups
+            .read()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or(AppError::NotFoundError)
+    }
+
+    async fn list_for_user(&self, user_id: &str, limit: usize) -> Result<Vec<Meetup>, AppError> {
+        let mut meetups: Vec<Meetup> = self
+            .meetups
+            .read()
+            .unwrap()
+            .values()
+            .filter(|meetup| meetup.has_participant(user_id))
+            .cloned()
+            .collect();
+        meetups.sort_by_key(|meetup| std::cmp::Reverse(meetup.created_at));
+        meetups.truncate(limit);
+        Ok(meetups)
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::UserStoreConfig, session::generate_token, AppError, Poi};
+
+mod cosmos;
+mod memory;
+mod sqlite;
+
+pub use cosmos::CosmosMeetupStore;
+pub use memory::InMemoryMeetupStore;
+pub use sqlite::SqliteMeetupStore;
+
+/// Users a group meetup can be organized with, besides the organizer.
+pub const MAX_GROUP_SIZE: usize = 9;
+/// Meetups returned when listing those of a user, most recent first.
+pub const MAX_LISTED_MEETUPS: usize = 50;
+
+/// A meeting point chosen for a group of users, visible to all of them.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Meetup {
+    pub id: String,
+    pub organizer_id: String,
+    /// Every user taking part, the organizer first.
+    pub participant_ids: Vec<String>,
+    /// The point as it was when chosen, later changes to the catalogue don't move the meetup.
+    pub poi: Poi,
+    pub created_at: DateTime<Utc>,
+}
+
+impl azure_data_cosmos::CosmosEntity for Meetup {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.id.clone()
+    }
+}
+
+impl Meetup {
+    pub fn new(organizer_id: &str, participant_ids: Vec<String>, poi: Poi) -> Self {
+        Meetup {
+            id: generate_token(24),
+            organizer_id: organizer_id.to_owned(),
+            participant_ids,
+            poi,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn has_participant(&self, user_id: &str) -> bool {
+        self.participant_ids.iter().any(|id| id == user_id)
+    }
+}
+
+/// Persistence layer for group meetups.
+#[async_trait]
+pub trait MeetupStore: Send + Sync {
+    async fn create(&self, meetup: &Meetup) -> Result<(), AppError>;
+
+    async fn get(&self, id: &str) -> Result<Meetup, AppError>;
+
+    /// The latest meetups the user takes part in, most recent first.
+    async fn list_for_user(&self, user_id: &str, limit: usize) -> Result<Vec<Meetup>, AppError>;
+}
+
+/// Builds the meetup store of the configured user store backend.
+pub async fn get_meetup_store(user_store_config: &UserStoreConfig) -> Arc<dyn MeetupStore> {
+    match user_store_config {
+        UserStoreConfig::Cosmos(cosmos_config) => Arc::new(CosmosMeetupStore::new(
+            crate::get_collection_client(cosmos_config, &cosmos_config.meetups_collection)
+                .await
+                .unwrap(),
+        )),
+        UserStoreConfig::Sqlite { path } => {
+            Arc::new(SqliteMeetupStore::open(path).expect("Could not open the SQLite database"))
+        }
+        UserStoreConfig::Memory => Arc::new(InMemoryMeetupStore::default()),
+    }
+}
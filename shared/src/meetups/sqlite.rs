@@ -0,0 +1,101 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{
+    store::sqlite::{open_connection, run_blocking},
+    AppError,
+};
+
+use super::{Meetup, MeetupStore};
+
+/// Meetup store backed by the same SQLite database as the user store.
+pub struct SqliteMeetupStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteMeetupStore {
+    pub fn open(path: &str) -> Result<Self, AppError> {
+        Ok(SqliteMeetupStore {
+            connection: Arc::new(Mutex::new(open_connection(path)?)),
+        })
+    }
+}
+
+/// Reads a meetup and its participants. The POI is a snapshot stored as JSON, it's never queried.
+fn load_meetup(connection: &Connection, id: &str) -> Result<Option<Meetup>, AppError> {
+    let Some((organizer_id, poi, created_at)) = connection
+        .query_row(
+            "SELECT organizer_id, poi, created_at FROM meetups WHERE id = ?1",
+            [id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get(2)?)),
+        )
+        .optional()?
+    else {
+        return Ok(None);
+    };
+    let participant_ids = connection
+        .prepare("SELECT user_id FROM meetup_participants WHERE meetup_id = ?1 ORDER BY position")?
+        .query_map([id], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+
+    Ok(Some(Meetup {
+        id: id.to_owned(),
+        organizer_id,
+        participant_ids,
+        poi: serde_json::from_str(&poi)?,
+        created_at,
+    }))
+}
+
+#[async_trait]
+impl MeetupStore for SqliteMeetupStore {
+    async fn create(&self, meetup: &Meetup) -> Result<(), AppError> {
+        let meetup = meetup.clone();
+        let poi = serde_json::to_string(&meetup.poi)?;
+        run_blocking(&self.connection, move |connection| {
+            let transaction = connection.transaction()?;
+            transaction.execute(
+                "INSERT INTO meetups (id, organizer_id, poi, created_at) VALUES (?1, ?2, ?3, ?4)",
+                params![meetup.id, meetup.organizer_id, poi, meetup.created_at],
+            )?;
+            for (position, user_id) in meetup.participant_ids.iter().enumerate() {
+                transaction.execute(
+                    "INSERT INTO meetup_participants (meetup_id, user_id, position) VALUES (?1, ?2, ?3)",
+                    params![meetup.id, user_id, position as i64],
+                )?;
+            }
+            transaction.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get(&self, id: &str) -> Result<Meetup, AppError> {
+        let id = id.to_owned();
+        run_blocking(&self.connection, move |connection| {
+            load_meetup(connection, &id)?.ok_or(AppError::NotFoundError)
+        })
+        .await
+    }
+
+    async fn list_for_user(&self, user_id: &str, limit: usize) -> Result<Vec<Meetup>, AppError> {
+        let user_id = user_id.to_owned();
+        run_blocking(&self.connection, move |connection| {
+            let ids = connection
+                .prepare(
+                    "SELECT m.id FROM meetups AS m
+                     JOIN meetup_participants AS p ON p.meetup_id = m.id
+                     WHERE p.user_id = ?1
+                     ORDER BY m.created_at DESC, m.id LIMIT ?2",
+                )?
+                .query_map(params![user_id, limit as i64], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()?;
+            ids.iter()
+                .filter_map(|id| load_meetup(connection, id).transpose())
+                .collect()
+        })
+        .await
+    }
+}
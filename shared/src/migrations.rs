@@ -0,0 +1,155 @@
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    config::UserStoreConfig,
+    matches::CosmosMatchStore,
+    session::{generate_token, hash_token},
+    store::{CosmosUserStore, SqliteUserStore},
+    get_vector_index, AppError, Config, MatchRecord, MatchStatus, MatchStore, Session,
+};
+
+/// Layout version of newly written user documents.
+pub const CURRENT_SCHEMA_VERSION: u32 = 3;
+
+type Step = fn(&mut Map<String, Value>, &Config) -> Result<(), AppError>;
+
+/// Upgrade steps of the user documents, the one at index `i` upgrading a document from version `i`
+/// to `i + 1`. New steps must only ever be appended to this list.
+const STEPS: &[Step] = &[hash_session_tokens, remove_embedded_matches, swap_location_axes];
+
+/// Upgrades a raw user document to `CURRENT_SCHEMA_VERSION`, returning whether it changed.
+pub fn upgrade_document(document: &mut Value, config: &Config) -> Result<bool, AppError> {
+    let object = document.as_object_mut().ok_or(AppError::GenericError)?;
+    let version = object
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .unwrap_or(0) as usize;
+    if version >= STEPS.len() {
+        return Ok(false);
+    }
+
+    for step in &STEPS[version..] {
+        step(object, config)?;
+    }
+    object.insert("schema_version".to_owned(), CURRENT_SCHEMA_VERSION.into());
+    Ok(true)
+}
+
+/// 0 -> 1: tokens are stored as keyed hashes.
+/// The single access token of documents predating sessions becomes a session of its own, which
+/// lasts as long as a new access token and can't be refreshed.
+fn hash_session_tokens(document: &mut Map<String, Value>, config: &Config) -> Result<(), AppError> {
+    let secret = &config.token_hash_secret;
+    let mut sessions = match document.remove("sessions") {
+        Some(Value::Array(sessions)) => sessions,
+        _ => Vec::new(),
+    };
+
+    for session in sessions.iter_mut().filter_map(Value::as_object_mut) {
+        for key in ["access_token", "refresh_token"] {
+            if let Some(Value::String(token)) = session.remove(key) {
+                session.insert(format!("{}_hash", key), hash_token(secret, &token).into());
+            }
+        }
+    }
+
+    if let Some(Value::String(access_token)) = document.remove("access_token") {
+        let now = Utc::now();
+        let legacy_session = Session {
+            id: generate_token(16),
+            device: None,
+            access_token_hash: hash_token(secret, &access_token),
+            refresh_token_hash: hash_token(secret, &generate_token(48)),
+            issued_at: now,
+            expires_at: now + config.access_token_ttl,
+            refresh_expires_at: now + config.access_token_ttl,
+            revoked_at: None,
+        };
+        sessions.push(serde_json::to_value(legacy_session)?);
+    }
+
+    document.insert("sessions".to_owned(), Value::Array(sessions));
+    Ok(())
+}
+
+/// 1 -> 2: matches are stored separately, see `extract_matches`.
+fn remove_embedded_matches(document: &mut Map<String, Value>, _config: &Config) -> Result<(), AppError> {
+    document.remove("matches");
+    Ok(())
+}
+
+/// 2 -> 3: locations are GeoJSON points, with the longitude first. They used to be stored latitude first.
+fn swap_location_axes(document: &mut Map<String, Value>, _config: &Config) -> Result<(), AppError> {
+    if let Some(Value::Array(coordinates)) = document
+        .get_mut("location")
+        .and_then(|location| location.get_mut("coordinates"))
+    {
+        coordinates.reverse();
+    }
+    Ok(())
+}
+
+/// A match as it was embedded in the document of each of its users.
+#[derive(Deserialize)]
+struct EmbeddedMatch {
+    /// Id of the other user.
+    id: String,
+    match_status: MatchStatus,
+}
+
+/// Copies the matches embedded in a raw user document to the match store, before the
+/// `remove_embedded_matches` step drops them. Both users had a copy of the match, and the copy of
+/// the user with the smallest id wins, since changes were always written to that side first.
+pub async fn extract_matches(document: &Value, match_store: &dyn MatchStore) -> Result<usize, AppError> {
+    let Some(user_id) = document.get("id").and_then(Value::as_str) else {
+        return Err(AppError::GenericError);
+    };
+    let embedded: Vec<EmbeddedMatch> = match document.get("matches") {
+        Some(matches) => serde_json::from_value(matches.clone())?,
+        None => return Ok(0),
+    };
+
+    for embedded_match in &embedded {
+        let record = MatchRecord::new(user_id, &embedded_match.id, embedded_match.match_status.clone());
+        match match_store.create(&record).await {
+            Err(AppError::Conflict) if user_id == record.user_ids[0] => {
+                let Some(mut existing) = match_store.get(user_id, &embedded_match.id).await? else {
+                    return Err(AppError::Conflict);
+                };
+                existing.document.status = record.status;
+                match_store.replace_if_match(&existing.document, &existing.etag).await?;
+            }
+            Err(AppError::Conflict) => {}
+            result => result?,
+        }
+    }
+    Ok(embedded.len())
+}
+
+/// Brings the configured user store up to date, for the `migrate` command of the server.
+pub async fn run(config: &Config) -> Result<(), AppError> {
+    match &config.user_store {
+        UserStoreConfig::Cosmos(cosmos_config) => {
+            let user_store = CosmosUserStore::new(
+                crate::get_collection_client(cosmos_config, &cosmos_config.users_collection).await?,
+            );
+            let match_store = CosmosMatchStore::new(
+                crate::get_collection_client(cosmos_config, &cosmos_config.matches_collection).await?,
+            );
+            let vector_index = get_vector_index(&config.vector_index);
+            let migrated = user_store
+                .migrate_documents(config, &match_store, &*vector_index)
+                .await?;
+            println!("Migrated {} user documents", migrated);
+        }
+        UserStoreConfig::Sqlite { path } => {
+            // The schema is migrated when the database is opened
+            SqliteUserStore::open(path)?;
+            println!("SQLite database {} is up to date", path);
+        }
+        UserStoreConfig::Memory => println!("The in-memory user store has nothing to migrate"),
+    }
+    Ok(())
+}
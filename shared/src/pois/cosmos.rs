@@ -0,0 +1,87 @@
+use async_trait::async_trait;
+use azure_data_cosmos::prelude::{CollectionClient, GetDocumentResponse, Param, Query};
+use futures::StreamExt;
+
+use crate::{AppError, GeoPoint};
+
+use super::{rank, NearbyPoi, Poi, PoiStore};
+
+/// POI store backed by a Cosmos DB collection, partitioned by POI id.
+pub struct CosmosPoiStore {
+    collection_client: CollectionClient,
+}
+
+impl CosmosPoiStore {
+    pub fn new(collection_client: CollectionClient) -> Self {
+        CosmosPoiStore { collection_client }
+    }
+
+    async fn query(&self, query: Query) -> Result<Vec<Poi>, AppError> {
+        let mut docs_stream = self
+            .collection_client
+            .query_documents(query)
+            .query_cross_partition(true)
+            .into_stream::<Poi>();
+
+        let mut pois = Vec::new();
+        while let Some(query_response) = docs_stream.next().await {
+            pois.extend(query_response?.results.into_iter().map(|(poi, _)| poi));
+        }
+        Ok(pois)
+    }
+}
+
+#[async_trait]
+impl PoiStore for CosmosPoiStore {
+    async fn get(&self, id: &str) -> Result<Poi, AppError> {
+        let response = self
+            .collection_client
+            .document_client(id, &id)?
+            .get_document::<Poi>()
+            .await?;
+
+        match response {
+            GetDocumentResponse::Found(document) => Ok(document.document.document),
+            _ => Err(AppError::NotFoundError),
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<Poi>, AppError> {
+        self.query(Query::new("SELECT * FROM pois".to_owned())).await
+    }
+
+    async fn upsert(&self, poi: &Poi) -> Result<(), AppError> {
+        self.collection_client
+            .create_document(poi.clone())
+            .is_upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), AppError> {
+        self.collection_client
+            .document_client(id, &id)?
+            .delete_document()
+            .await?;
+        Ok(())
+    }
+
+    async fn nearest(
+        &self,
+        center: &GeoPoint,
+        radius_km: f64,
+        limit: usize,
+    ) -> Result<Vec<NearbyPoi>, AppError> {
+        // Locations are GeoJSON points, so the spatial functions of Cosmos DB apply to them directly
+        let candidates = self
+            .query(Query::with_params(
+                "SELECT * FROM pois AS p WHERE ST_DISTANCE(p.location, @center) <= @radius_m".to_owned(),
+                vec![
+                    Param::new("@center".into(), *center),
+                    Param::new("@radius_m".into(), radius_km * 1000.),
+                ],
+            ))
+            .await?;
+        Ok(rank(candidates, center, radius_km, limit))
+    }
+}
@@ -0,0 +1,141 @@
+//! Reading points of interest from GeoJSON or CSV files.
+//!
+//! GeoJSON files are a `FeatureCollection` of `Point` features, with the other fields of the POI
+//! in the properties. CSV files have a header row with the columns
+//! `id,name,address,category,opening_hours,partner,latitude,longitude`, and optionally `timezone`.
+//! In both formats only the name and the coordinates are required. POIs without an id get a random
+//! one, so importing them again creates duplicates.
+
+use std::{fs, path::Path};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{session::generate_token, AppError, GeoPoint};
+
+use super::{Poi, PoiCategory, PoiStore};
+
+/// Supported file formats.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PoiFormat {
+    GeoJson,
+    Csv,
+}
+
+#[derive(Deserialize)]
+struct FeatureCollection {
+    features: Vec<Feature>,
+}
+
+#[derive(Deserialize)]
+struct Feature {
+    /// GeoJSON allows both strings and numbers.
+    id: Option<Value>,
+    geometry: GeoPoint,
+    properties: FeatureProperties,
+}
+
+#[derive(Deserialize)]
+struct FeatureProperties {
+    id: Option<String>,
+    name: String,
+    address: Option<String>,
+    #[serde(default)]
+    category: PoiCategory,
+    opening_hours: Option<String>,
+    #[serde(default)]
+    partner: bool,
+    timezone: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CsvRow {
+    id: Option<String>,
+    name: String,
+    address: Option<String>,
+    category: Option<String>,
+    opening_hours: Option<String>,
+    partner: Option<bool>,
+    latitude: f64,
+    longitude: f64,
+    timezone: Option<String>,
+}
+
+fn invalid(err: impl std::fmt::Debug) -> AppError {
+    println!("Invalid POI data: {:?}", err);
+    AppError::InvalidPoiData
+}
+
+pub fn from_geojson(data: &str) -> Result<Vec<Poi>, AppError> {
+    let collection: FeatureCollection = serde_json::from_str(data).map_err(invalid)?;
+    Ok(collection
+        .features
+        .into_iter()
+        .map(|feature| {
+            let feature_id = feature.id.map(|id| match id {
+                Value::String(id) => id,
+                id => id.to_string(),
+            });
+            Poi {
+                id: feature
+                    .properties
+                    .id
+                    .or(feature_id)
+                    .unwrap_or_else(|| generate_token(16)),
+                name: feature.properties.name,
+                address: feature.properties.address,
+                category: feature.properties.category,
+                opening_hours: feature.properties.opening_hours,
+                partner: feature.properties.partner,
+                location: feature.geometry,
+                timezone: feature.properties.timezone,
+            }
+        })
+        .collect())
+}
+
+pub fn from_csv(data: &str) -> Result<Vec<Poi>, AppError> {
+    csv::Reader::from_reader(data.as_bytes())
+        .deserialize::<CsvRow>()
+        .map(|row| {
+            let row = row.map_err(invalid)?;
+            Ok(Poi {
+                id: row.id.unwrap_or_else(|| generate_token(16)),
+                name: row.name,
+                address: row.address,
+                category: row.category.as_deref().map(PoiCategory::from_name).unwrap_or_default(),
+                opening_hours: row.opening_hours,
+                partner: row.partner.unwrap_or(false),
+                location: GeoPoint::new(row.latitude, row.longitude).map_err(invalid)?,
+                timezone: row.timezone,
+            })
+        })
+        .collect()
+}
+
+pub fn parse(data: &str, format: PoiFormat) -> Result<Vec<Poi>, AppError> {
+    match format {
+        PoiFormat::GeoJson => from_geojson(data),
+        PoiFormat::Csv => from_csv(data),
+    }
+}
+
+/// Reads a file, in the format given by its extension (`.csv`, GeoJSON otherwise).
+pub fn from_file(path: &str) -> Result<Vec<Poi>, AppError> {
+    let data = fs::read_to_string(path).map_err(invalid)?;
+    let format = match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+        Some("csv") => PoiFormat::Csv,
+        _ => PoiFormat::GeoJson,
+    };
+    parse(&data, format)
+}
+
+/// Reads a file and saves its POIs, replacing the ones with the same id.
+pub async fn import_file(poi_store: &dyn PoiStore, path: &str) -> Result<usize, AppError> {
+    let pois = from_file(path)?;
+    for poi in &pois {
+        poi_store.upsert(poi).await?;
+    }
+    Ok(pois.len())
+}
@@ -0,0 +1,53 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use async_trait::async_trait;
+
+use crate::{AppError, GeoPoint};
+
+use super::{rank, NearbyPoi, Poi, PoiStore};
+
+/// POI store keeping the whole catalogue in memory, nothing is persisted.
+#[derive(Default)]
+pub struct InMemoryPoiStore {
+    pois: RwLock<HashMap<String, Poi>>,
+}
+
+#[async_trait]
+impl PoiStore for InMemoryPoiStore {
+    async fn get(&self, id: &str) -> Result<Poi, AppError> {
+        self.pois
+            .read()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or(AppError::NotFoundError)
+    }
+
+    async fn list(&self) -> Result<Vec<Poi>, AppError> {
+        Ok(self.pois.read().unwrap().values().cloned().collect())
+    }
+
+    async fn upsert(&self, poi: &Poi) -> Result<(), AppError> {
+        self.pois.write().unwrap().insert(poi.id.clone(), poi.clone());
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), AppError> {
+        self.pois
+            .write()
+            .unwrap()
+            .remove(id)
+            .map(|_| ())
+            .ok_or(AppError::NotFoundError)
+    }
+
+    async fn nearest(
+        &self,
+        center: &GeoPoint,
+        radius_km: f64,
+        limit: usize,
+    ) -> Result<Vec<NearbyPoi>, AppError> {
+        let pois = self.pois.read().unwrap().values().cloned().collect::<Vec<_>>();
+        Ok(rank(pois, center, radius_km, limit))
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::UserStoreConfig, geodesy::haversine_km, AppError, GeoPoint};
+
+mod cosmos;
+pub mod import;
+pub mod selection;
+mod memory;
+mod sqlite;
+
+pub use cosmos::CosmosPoiStore;
+pub use memory::InMemoryPoiStore;
+pub use sqlite::SqlitePoiStore;
+
+/// Radius searched around a location when none is given, in km.
+pub const DEFAULT_POI_RADIUS_KM: f64 = 5.;
+/// Farthest a point of interest can be from the location searched around, in km.
+pub const MAX_POI_RADIUS_KM: f64 = 50.;
+pub const DEFAULT_POI_RESULTS: usize = 10;
+pub const MAX_POI_RESULTS: usize = 50;
+
+/// Kind of place, used by clients to pick an icon and by users to filter.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PoiCategory {
+    Cafe,
+    Restaurant,
+    Bar,
+    Park,
+    Museum,
+    Library,
+    Shop,
+    #[default]
+    #[serde(other)]
+    Other,
+}
+
+impl PoiCategory {
+    /// Name stored in the database, the same as the serialized one.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PoiCategory::Cafe => "cafe",
+            PoiCategory::Restaurant => "restaurant",
+            PoiCategory::Bar => "bar",
+            PoiCategory::Park => "park",
+            PoiCategory::Museum => "museum",
+            PoiCategory::Library => "library",
+            PoiCategory::Shop => "shop",
+            PoiCategory::Other => "other",
+        }
+    }
+
+    /// Unknown names fall back to [`PoiCategory::Other`].
+    pub fn from_name(value: &str) -> PoiCategory {
+        serde_json::from_value(serde_json::Value::String(value.to_owned())).unwrap_or_default()
+    }
+}
+
+/// A place users can be suggested to meet at.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Poi {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub category: PoiCategory,
+    /// In the OpenStreetMap `opening_hours` format, e.g. `Mo-Fr 08:00-18:00; Sa 09:00-13:00`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opening_hours: Option<String>,
+    /// Partner businesses, which pay to act as meeting points.
+    #[serde(default)]
+    pub partner: bool,
+    pub location: GeoPoint,
+    /// IANA time zone of the place (e.g. `Europe/Rome`), for the times of calendar events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+impl azure_data_cosmos::CosmosEntity for Poi {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.id.clone()
+    }
+}
+
+/// A point of interest found around a location.
+#[derive(Serialize, Clone, Debug)]
+pub struct NearbyPoi {
+    #[serde(flatten)]
+    pub poi: Poi,
+    /// Great-circle distance from the location searched around.
+    pub distance_km: f64,
+}
+
+/// Persistence layer for the catalogue of points of interest.
+#[async_trait]
+pub trait PoiStore: Send + Sync {
+    async fn get(&self, id: &str) -> Result<Poi, AppError>;
+
+    async fn list(&self) -> Result<Vec<Poi>, AppError>;
+
+    /// Creates the point, or replaces the one with the same id.
+    async fn upsert(&self, poi: &Poi) -> Result<(), AppError>;
+
+    async fn delete(&self, id: &str) -> Result<(), AppError>;
+
+    /// Up to `limit` points within `radius_km` of `center`, nearest first.
+    async fn nearest(
+        &self,
+        center: &GeoPoint,
+        radius_km: f64,
+        limit: usize,
+    ) -> Result<Vec<NearbyPoi>, AppError>;
+}
+
+/// Keeps the candidates within the radius, nearest first. Stores only narrow down the candidates
+/// roughly (e.g. with a bounding box), the exact distance is always computed here.
+fn rank(
+    candidates: impl IntoIterator<Item = Poi>,
+    center: &GeoPoint,
+    radius_km: f64,
+    limit: usize,
+) -> Vec<NearbyPoi> {
+    let mut nearby: Vec<NearbyPoi> = candidates
+        .into_iter()
+        .map(|poi| NearbyPoi {
+            distance_km: haversine_km(center, &poi.location),
+            poi,
+        })
+        .filter(|nearby| nearby.distance_km <= radius_km)
+        .collect();
+    nearby.sort_by(|a, b| a.distance_km.total_cmp(&b.distance_km));
+    nearby.truncate(limit);
+    nearby
+}
+
+/// Builds the POI store of the configured user store backend.
+pub async fn get_poi_store(user_store_config: &UserStoreConfig) -> Arc<dyn PoiStore> {
+    match user_store_config {
+        UserStoreConfig::Cosmos(cosmos_config) => Arc::new(CosmosPoiStore::new(
+            crate::get_collection_client(cosmos_config, &cosmos_config.pois_collection)
+                .await
+                .unwrap(),
+        )),
+        UserStoreConfig::Sqlite { path } => {
+            Arc::new(SqlitePoiStore::open(path).expect("Could not open the SQLite database"))
+        }
+        UserStoreConfig::Memory => Arc::new(InMemoryPoiStore::default()),
+    }
+}
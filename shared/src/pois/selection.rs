@@ -0,0 +1,212 @@
+//! Choice of the point of interest a group of users meets at.
+//!
+//! Candidates are the points of interest around the center of the participants, ranked by the cost
+//! a [`SelectionStrategy`] gives them. Returning a shortlist rather than a single point lets users
+//! pick the place that suits them best among the fair ones.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    geodesy::{centroid, haversine_km},
+    AppError, GeoPoint,
+};
+
+use super::{Poi, PoiStore, MAX_POI_RADIUS_KM};
+
+/// Points of interest around the center of the participants considered by the strategies.
+const MAX_CANDIDATES: usize = 200;
+/// Candidates are searched this far beyond the participant farthest from the center, in km, so that
+/// participants far apart still get the points of interest around and between them.
+const CANDIDATE_MARGIN_KM: f64 = MAX_POI_RADIUS_KM;
+pub const DEFAULT_SHORTLIST_SIZE: usize = 3;
+pub const MAX_SHORTLIST_SIZE: usize = 10;
+
+/// A user taking part in a meeting.
+#[derive(Clone, Debug)]
+pub struct Participant {
+    pub user_id: String,
+    pub location: GeoPoint,
+}
+
+/// How far a candidate point is for each participant.
+#[derive(Serialize, Clone, Debug)]
+pub struct ParticipantDistance {
+    pub user_id: String,
+    pub distance_km: f64,
+}
+
+/// A point of interest proposed as a meeting point.
+#[derive(Serialize, Clone, Debug)]
+pub struct MeetingPointCandidate {
+    pub poi: Poi,
+    /// In the same order as the participants.
+    pub distances: Vec<ParticipantDistance>,
+}
+
+/// Ranks the candidate meeting points, the lower the cost the better.
+pub trait SelectionStrategy: Send + Sync {
+    /// `distances_km` are the distances of the candidate from each participant, in the same order.
+    fn cost(&self, candidate: &GeoPoint, participants: &[Participant], distances_km: &[f64]) -> f64;
+}
+
+/// The point closest to the center of the participants, regardless of how far each one has to go.
+pub struct NearestToMidpoint;
+
+impl SelectionStrategy for NearestToMidpoint {
+    fn cost(&self, candidate: &GeoPoint, participants: &[Participant], _: &[f64]) -> f64 {
+        let locations = participants.iter().map(|participant| participant.location).collect::<Vec<_>>();
+        haversine_km(candidate, &centroid(&locations))
+    }
+}
+
+/// The point the participant farthest from it has the shortest way to, the fairest one.
+pub struct MinimizeMaxDistance;
+
+impl SelectionStrategy for MinimizeMaxDistance {
+    fn cost(&self, _: &GeoPoint, _: &[Participant], distances_km: &[f64]) -> f64 {
+        distances_km.iter().copied().fold(0., f64::max)
+    }
+}
+
+/// The point the participants travel the least to in total, i.e. the geometric median of their locations
+/// among the points of interest, even if one travels much more than the others.
+pub struct MinimizeTotalDistance;
+
+impl SelectionStrategy for MinimizeTotalDistance {
+    fn cost(&self, _: &GeoPoint, _: &[Participant], distances_km: &[f64]) -> f64 {
+        distances_km.iter().sum()
+    }
+}
+
+/// Strategies clients can choose from.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MeetingStrategy {
+    #[default]
+    NearestToMidpoint,
+    MinimizeMaxDistance,
+    MinimizeTotalDistance,
+}
+
+impl MeetingStrategy {
+    pub fn strategy(self) -> &'static dyn SelectionStrategy {
+        match self {
+            MeetingStrategy::NearestToMidpoint => &NearestToMidpoint,
+            MeetingStrategy::MinimizeMaxDistance => &MinimizeMaxDistance,
+            MeetingStrategy::MinimizeTotalDistance => &MinimizeTotalDistance,
+        }
+    }
+}
+
+/// Up to `limit` meeting points for the participants, best first according to the strategy.
+/// Ties are broken by the total distance, so that no one travels for nothing.
+pub async fn shortlist(
+    poi_store: &dyn PoiStore,
+    participants: &[Participant],
+    strategy: &dyn SelectionStrategy,
+    limit: usize,
+) -> Result<Vec<MeetingPointCandidate>, AppError> {
+    let locations = participants.iter().map(|participant| participant.location).collect::<Vec<_>>();
+    let center = centroid(&locations);
+    let spread_km = locations
+        .iter()
+        .map(|location| haversine_km(&center, location))
+        .fold(0., f64::max);
+    let candidates = poi_store.nearest(&center, spread_km + CANDIDATE_MARGIN_KM, MAX_CANDIDATES).await?;
+
+    let mut ranked = candidates
+        .into_iter()
+        .map(|nearby| {
+            let distances_km = locations
+                .iter()
+                .map(|location| haversine_km(location, &nearby.poi.location))
+                .collect::<Vec<_>>();
+            let cost = strategy.cost(&nearby.poi.location, participants, &distances_km);
+            let total = distances_km.iter().sum::<f64>();
+            (cost, total, nearby.poi, distances_km)
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+    Ok(ranked
+        .into_iter()
+        .take(limit)
+        .map(|(_, _, poi, distances_km)| MeetingPointCandidate {
+            poi,
+            distances: participants
+                .iter()
+                .zip(distances_km)
+                .map(|(participant, distance_km)| ParticipantDistance {
+                    user_id: participant.user_id.clone(),
+                    distance_km,
+                })
+                .collect(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pois::{InMemoryPoiStore, PoiCategory};
+
+    use super::*;
+
+    fn participant(user_id: &str, latitude: f64, longitude: f64) -> Participant {
+        Participant {
+            user_id: user_id.to_owned(),
+            location: GeoPoint::new(latitude, longitude).unwrap(),
+        }
+    }
+
+    async fn store(pois: &[(&str, f64, f64)]) -> InMemoryPoiStore {
+        let store = InMemoryPoiStore::default();
+        for &(id, latitude, longitude) in pois {
+            let poi = Poi {
+                id: id.to_owned(),
+                name: id.to_owned(),
+                address: None,
+                category: PoiCategory::default(),
+                opening_hours: None,
+                partner: false,
+                location: GeoPoint::new(latitude, longitude).unwrap(),
+                timezone: None,
+            };
+            store.upsert(&poi).await.unwrap();
+        }
+        store
+    }
+
+    fn ids(candidates: &[MeetingPointCandidate]) -> Vec<&str> {
+        candidates.iter().map(|candidate| candidate.poi.id.as_str()).collect()
+    }
+
+    #[test]
+    fn nearest_to_midpoint_is_the_default() {
+        assert_eq!(MeetingStrategy::default(), MeetingStrategy::NearestToMidpoint);
+    }
+
+    #[tokio::test]
+    async fn participants_far_apart_get_the_points_between_them() {
+        // Milan and Rome, with the only point of interest in Bologna, about 100 km from their midpoint
+        let participants = [participant("a", 45.4642, 9.19), participant("b", 41.9028, 12.4964)];
+        let store = store(&[("bologna", 44.4949, 11.3426)]).await;
+
+        let candidates = shortlist(&store, &participants, &NearestToMidpoint, 3).await.unwrap();
+        assert_eq!(ids(&candidates), ["bologna"]);
+        assert_eq!(candidates[0].distances.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn strategies_rank_the_candidates() {
+        // Two users 10 km apart on a meridian, a point next to the first one and one midway off to the side
+        let participants = [participant("a", 45., 9.), participant("b", 45.09, 9.)];
+        let store = store(&[("near_a", 45.001, 9.), ("midway", 45.045, 9.03)]).await;
+
+        let fair = shortlist(&store, &participants, &MinimizeMaxDistance, 3).await.unwrap();
+        assert_eq!(ids(&fair), ["midway", "near_a"]);
+        let total = shortlist(&store, &participants, &MinimizeTotalDistance, 3).await.unwrap();
+        assert_eq!(ids(&total), ["near_a", "midway"]);
+        let limited = shortlist(&store, &participants, &NearestToMidpoint, 1).await.unwrap();
+        assert_eq!(ids(&limited), ["midway"]);
+    }
+}
@@ -0,0 +1,168 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::{
+    geodesy::BoundingBox,
+    store::sqlite::{open_connection, run_blocking},
+    AppError, GeoPoint,
+};
+
+use super::{rank, NearbyPoi, Poi, PoiCategory, PoiStore};
+
+const COLUMNS: &str = "id, name, address, category, opening_hours, partner, latitude, longitude, timezone";
+
+/// POI store backed by the same SQLite database as the user store.
+pub struct SqlitePoiStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqlitePoiStore {
+    pub fn open(path: &str) -> Result<Self, AppError> {
+        Ok(SqlitePoiStore {
+            connection: Arc::new(Mutex::new(open_connection(path)?)),
+        })
+    }
+}
+
+/// A row selected with [`COLUMNS`].
+struct PoiRow {
+    id: String,
+    name: String,
+    address: Option<String>,
+    category: String,
+    opening_hours: Option<String>,
+    partner: bool,
+    latitude: f64,
+    longitude: f64,
+    timezone: Option<String>,
+}
+
+impl PoiRow {
+    fn read(row: &Row) -> rusqlite::Result<Self> {
+        Ok(PoiRow {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            address: row.get(2)?,
+            category: row.get(3)?,
+            opening_hours: row.get(4)?,
+            partner: row.get(5)?,
+            latitude: row.get(6)?,
+            longitude: row.get(7)?,
+            timezone: row.get(8)?,
+        })
+    }
+
+    fn into_poi(self) -> Result<Poi, AppError> {
+        Ok(Poi {
+            id: self.id,
+            name: self.name,
+            address: self.address,
+            category: PoiCategory::from_name(&self.category),
+            opening_hours: self.opening_hours,
+            partner: self.partner,
+            location: GeoPoint::new(self.latitude, self.longitude)?,
+            timezone: self.timezone,
+        })
+    }
+}
+
+/// Reads all the rows of a query selecting [`COLUMNS`].
+fn query_pois(connection: &Connection, sql: &str, params: impl rusqlite::Params) -> Result<Vec<Poi>, AppError> {
+    let mut statement = connection.prepare(sql)?;
+    let rows = statement.query_map(params, PoiRow::read)?.collect::<Result<Vec<_>, _>>()?;
+    rows.into_iter().map(PoiRow::into_poi).collect()
+}
+
+#[async_trait]
+impl PoiStore for SqlitePoiStore {
+    async fn get(&self, id: &str) -> Result<Poi, AppError> {
+        let id = id.to_owned();
+        run_blocking(&self.connection, move |connection| {
+            connection
+                .query_row(
+                    &format!("SELECT {} FROM pois WHERE id = ?1", COLUMNS),
+                    [&id],
+                    PoiRow::read,
+                )
+                .optional()?
+                .ok_or(AppError::NotFoundError)?
+                .into_poi()
+        })
+        .await
+    }
+
+    async fn list(&self) -> Result<Vec<Poi>, AppError> {
+        run_blocking(&self.connection, move |connection| {
+            query_pois(connection, &format!("SELECT {} FROM pois ORDER BY name", COLUMNS), [])
+        })
+        .await
+    }
+
+    async fn upsert(&self, poi: &Poi) -> Result<(), AppError> {
+        let poi = poi.clone();
+        run_blocking(&self.connection, move |connection| {
+            connection.execute(
+                &format!(
+                    "INSERT OR REPLACE INTO pois ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    COLUMNS
+                ),
+                params![
+                    poi.id,
+                    poi.name,
+                    poi.address,
+                    poi.category.as_str(),
+                    poi.opening_hours,
+                    poi.partner,
+                    poi.location.latitude(),
+                    poi.location.longitude(),
+                    poi.timezone,
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), AppError> {
+        let id = id.to_owned();
+        run_blocking(&self.connection, move |connection| {
+            match connection.execute("DELETE FROM pois WHERE id = ?1", [&id])? {
+                0 => Err(AppError::NotFoundError),
+                _ => Ok(()),
+            }
+        })
+        .await
+    }
+
+    async fn nearest(
+        &self,
+        center: &GeoPoint,
+        radius_km: f64,
+        limit: usize,
+    ) -> Result<Vec<NearbyPoi>, AppError> {
+        let center = *center;
+        let bounds = BoundingBox::around(&center, radius_km);
+        run_blocking(&self.connection, move |connection| {
+            // A box whose minimum longitude is greater than the maximum one crosses the antimeridian
+            let candidates = query_pois(
+                connection,
+                &format!(
+                    "SELECT {} FROM pois
+                     WHERE latitude BETWEEN ?1 AND ?2
+                        AND CASE WHEN ?3 <= ?4 THEN longitude BETWEEN ?3 AND ?4 ELSE longitude >= ?3 OR longitude <= ?4 END",
+                    COLUMNS
+                ),
+                params![
+                    bounds.min_latitude,
+                    bounds.max_latitude,
+                    bounds.min_longitude,
+                    bounds.max_longitude,
+                ],
+            )?;
+            Ok(rank(candidates, &center, radius_km, limit))
+        })
+        .await
+    }
+}
@@ -0,0 +1,114 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{visibility::visible_location, AppError, MatchPage, MatchRecord, MatchStatus, GeoPoint, UserDocument, UserStore};
+
+/// What other users can see of a user, e.g. in search results and matches.
+#[derive(Serialize, Clone, Debug)]
+pub struct PublicProfile {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+}
+
+impl From<&UserDocument> for PublicProfile {
+    fn from(user_document: &UserDocument) -> Self {
+        PublicProfile {
+            id: user_document.id.clone(),
+            name: user_document.name.clone(),
+            description: user_document.description.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// A match as seen by one of the two users, showing the current public profile of the other one.
+#[derive(Serialize, Clone, Debug)]
+pub struct MatchView {
+    #[serde(flatten)]
+    pub user: PublicProfile,
+    pub match_status: MatchStatus,
+    /// Location of the other user, as precise as the status of the match allows.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<GeoPoint>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl MatchView {
+    /// The match as seen by `user`, `other_user` being the other user of the match.
+    pub fn new(record: &MatchRecord, user: &UserDocument, other_user: &UserDocument) -> Self {
+        MatchView {
+            user: PublicProfile::from(other_user),
+            match_status: record.status_for(&user.id),
+            location: visible_location(user, other_user, Some(record)),
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        }
+    }
+}
+
+/// A page of the matches of a user, with the profiles of the other users.
+#[derive(Serialize, Clone, Debug)]
+pub struct MatchViewPage {
+    pub matches: Vec<MatchView>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl MatchViewPage {
+    /// Loads the profiles of the other users of each match. Matches with users which were deleted
+    /// in the meantime are left out.
+    pub async fn load(user_store: &dyn UserStore, user: &UserDocument, page: MatchPage) -> Result<Self, AppError> {
+        let mut matches = Vec::with_capacity(page.records.len());
+        for record in &page.records {
+            match user_store.get_by_id(record.other_user(&user.id)).await {
+                Ok(other_user) => matches.push(MatchView::new(record, user, &other_user)),
+                Err(AppError::NotFoundError) => println!("Skipping match {} with a deleted user", record.id),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(MatchViewPage {
+            matches,
+            next_cursor: page.next_cursor,
+        })
+    }
+}
+
+/// Profile of the authenticated user, only ever returned to the user themselves.
+/// Internal data (sessions, embeddings) is left out.
+#[derive(Serialize, Clone, Debug)]
+pub struct MyProfile {
+    pub id: String,
+    pub email: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<GeoPoint>,
+    /// Identity providers the user can log in with.
+    pub providers: Vec<String>,
+    /// First page of the matches of the user, the following ones are listed by the match function.
+    pub matches: Vec<MatchView>,
+    /// Cursor of the second page of matches, if there's one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matches_next_cursor: Option<String>,
+}
+
+impl MyProfile {
+    pub fn new(user_document: &UserDocument, matches: MatchViewPage) -> Self {
+        MyProfile {
+            id: user_document.id.clone(),
+            email: user_document.email.clone(),
+            name: user_document.name.clone(),
+            description: user_document.description.clone(),
+            location: user_document.location,
+            providers: user_document
+                .identities
+                .iter()
+                .map(|identity| identity.provider.clone())
+                .collect(),
+            matches: matches.matches,
+            matches_next_cursor: matches.next_cursor,
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{AppError, Config};
+
+/// Proof that a user was recommended to another one by the search, required to send them a match
+/// request. It's a signature of both user ids and an expiration time, so nothing needs to be stored:
+/// `<expiration as unix seconds>.<HMAC-SHA256, base64url encoded>`.
+pub struct RecommendationTicket;
+
+fn signature(secret: &str, user_id: &str, target_id: &str, expires_at: i64) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    // Prefixed so that a ticket can never be mistaken for a token hash signed with the same secret
+    mac.update(format!("recommendation:{}:{}:{}", user_id, target_id, expires_at).as_bytes());
+    mac
+}
+
+impl RecommendationTicket {
+    /// Ticket allowing `user_id` to send a match request to `target_id`.
+    pub fn issue(config: &Config, user_id: &str, target_id: &str) -> String {
+        let expires_at = (Utc::now() + config.recommendation_ticket_ttl).timestamp();
+        let mac = signature(&config.token_hash_secret, user_id, target_id, expires_at);
+        format!(
+            "{}.{}",
+            expires_at,
+            URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+        )
+    }
+
+    /// Checks that the ticket was issued to `user_id` for `target_id`, and hasn't expired.
+    pub fn verify(config: &Config, ticket: &str, user_id: &str, target_id: &str) -> Result<(), AppError> {
+        let (expires_at, encoded) = ticket.split_once('.').ok_or(AppError::InvalidTicket)?;
+        let expires_at: i64 = expires_at.parse().map_err(|_| AppError::InvalidTicket)?;
+        let decoded = URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map_err(|_| AppError::InvalidTicket)?;
+
+        signature(&config.token_hash_secret, user_id, target_id, expires_at)
+            .verify_slice(&decoded)
+            .map_err(|_| {
+                println!("Invalid recommendation ticket from {} for {}", user_id, target_id);
+                AppError::InvalidTicket
+            })?;
+        if expires_at < Utc::now().timestamp() {
+            println!("Expired recommendation ticket from {} for {}", user_id, target_id);
+            return Err(AppError::InvalidTicket);
+        }
+        Ok(())
+    }
+}
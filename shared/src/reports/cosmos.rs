@@ -0,0 +1,27 @@
+use async_trait::async_trait;
+use azure_data_cosmos::prelude::CollectionClient;
+
+use crate::AppError;
+
+use super::{Report, ReportStore};
+
+/// Report store backed by a Cosmos DB collection, partitioned by report id.
+pub struct CosmosReportStore {
+    collection_client: CollectionClient,
+}
+
+impl CosmosReportStore {
+    pub fn new(collection_client: CollectionClient) -> Self {
+        CosmosReportStore { collection_client }
+    }
+}
+
+#[async_trait]
+impl ReportStore for CosmosReportStore {
+    async fn create(&self, report: &Report) -> Result<(), AppError> {
+        self.collection_client
+            .create_document(report.clone())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,21 @@
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+
+use crate::AppError;
+
+use super::{Report, ReportStore};
+
+/// Report store keeping every report in memory, nothing is persisted.
+#[derive(Default)]
+pub struct InMemoryReportStore {
+    reports: RwLock<Vec<Report>>,
+}
+
+#[async_trait]
+impl ReportStore for InMemoryReportStore {
+    async fn create(&self, report: &Report) -> Result<(), AppError> {
+        self.reports.write().unwrap().push(report.clone());
+        Ok(())
+    }
+}
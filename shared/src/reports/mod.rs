@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::UserStoreConfig, session::generate_token, AppError};
+
+mod cosmos;
+mod memory;
+mod sqlite;
+
+pub use cosmos::CosmosReportStore;
+pub use memory::InMemoryReportStore;
+pub use sqlite::SqliteReportStore;
+
+/// Maximum length of the free text attached to a report.
+pub const MAX_DETAILS_LENGTH: usize = 1000;
+
+/// Why a user was reported.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportReason {
+    Spam,
+    Harassment,
+    InappropriateContent,
+    FakeProfile,
+    Underage,
+    Other,
+}
+
+impl ReportReason {
+    /// Name stored in the database, the same as the serialized one.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportReason::Spam => "spam",
+            ReportReason::Harassment => "harassment",
+            ReportReason::InappropriateContent => "inappropriate_content",
+            ReportReason::FakeProfile => "fake_profile",
+            ReportReason::Underage => "underage",
+            ReportReason::Other => "other",
+        }
+    }
+}
+
+/// A user flagging another one for moderation. Reports are only written by the backend, and reviewed
+/// directly in the database.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Report {
+    pub id: String,
+    pub reporter_id: String,
+    pub reported_user_id: String,
+    pub reason: ReportReason,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl azure_data_cosmos::CosmosEntity for Report {
+    type Entity = String;
+
+    fn partition_key(&self) -> Self::Entity {
+        self.id.clone()
+    }
+}
+
+impl Report {
+    pub fn new(
+        reporter_id: &str,
+        reported_user_id: &str,
+        reason: ReportReason,
+        details: Option<String>,
+    ) -> Self {
+        Report {
+            id: generate_token(24),
+            reporter_id: reporter_id.to_owned(),
+            reported_user_id: reported_user_id.to_owned(),
+            reason,
+            details,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Persistence layer for reports.
+#[async_trait]
+pub trait ReportStore: Send + Sync {
+    async fn create(&self, report: &Report) -> Result<(), AppError>;
+}
+
+/// Builds the report store of the configured user store backend.
+pub async fn get_report_store(user_store_config: &UserStoreConfig) -> Arc<dyn ReportStore> {
+    match user_store_config {
+        UserStoreConfig::Cosmos(cosmos_config) => Arc::new(CosmosReportStore::new(
+            crate::get_collection_client(cosmos_config, &cosmos_config.reports_collection)
+                .await
+                .unwrap(),
+        )),
+        UserStoreConfig::Sqlite { path } => {
+            Arc::new(SqliteReportStore::open(path).expect("Could not open the SQLite database"))
+        }
+        UserStoreConfig::Memory => Arc::new(InMemoryReportStore::default()),
+    }
+}
@@ -0,0 +1,47 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use rusqlite::{params, Connection};
+
+use crate::{
+    store::sqlite::{open_connection, run_blocking},
+    AppError,
+};
+
+use super::{Report, ReportStore};
+
+/// Report store backed by the same SQLite database as the user store.
+pub struct SqliteReportStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteReportStore {
+    pub fn open(path: &str) -> Result<Self, AppError> {
+        Ok(SqliteReportStore {
+            connection: Arc::new(Mutex::new(open_connection(path)?)),
+        })
+    }
+}
+
+#[async_trait]
+impl ReportStore for SqliteReportStore {
+    async fn create(&self, report: &Report) -> Result<(), AppError> {
+        let report = report.clone();
+        run_blocking(&self.connection, move |connection| {
+            connection.execute(
+                "INSERT INTO reports (id, reporter_id, reported_user_id, reason, details, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    report.id,
+                    report.reporter_id,
+                    report.reported_user_id,
+                    report.reason.as_str(),
+                    report.details,
+                    report.created_at,
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+}
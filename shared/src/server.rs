@@ -0,0 +1,58 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::Router;
+
+use crate::{
+    get_embedding_provider, get_match_store, get_meeting_store, get_meetup_store, get_poi_store,
+    get_report_store, get_user_store, get_vector_index, identity::IdentityProviders, Config,
+    EmbeddingProvider, MatchStore, MeetingStore, MeetupStore, PoiStore, ReportStore, UserStore,
+    VectorIndex,
+};
+
+/// State shared by the routers of every function, so that they can be served both as separate
+/// Azure Functions and together by a single server process.
+pub struct AppState {
+    pub config: Config,
+    pub user_store: Arc<dyn UserStore>,
+    pub match_store: Arc<dyn MatchStore>,
+    pub report_store: Arc<dyn ReportStore>,
+    pub poi_store: Arc<dyn PoiStore>,
+    pub meetup_store: Arc<dyn MeetupStore>,
+    pub meeting_store: Arc<dyn MeetingStore>,
+    pub vector_index: Arc<dyn VectorIndex>,
+    pub embedding_provider: Arc<dyn EmbeddingProvider>,
+    pub identity_providers: IdentityProviders,
+}
+
+impl AppState {
+    pub async fn new(config: Config) -> Self {
+        AppState {
+            user_store: get_user_store(&config.user_store).await,
+            match_store: get_match_store(&config.user_store).await,
+            report_store: get_report_store(&config.user_store).await,
+            poi_store: get_poi_store(&config.user_store).await,
+            meetup_store: get_meetup_store(&config.user_store).await,
+            meeting_store: get_meeting_store(&config.user_store).await,
+            vector_index: get_vector_index(&config.vector_index),
+            embedding_provider: get_embedding_provider(&config.embeddings),
+            identity_providers: IdentityProviders::new(&config),
+            config,
+        }
+    }
+}
+
+/// Address an Azure Functions custom handler must listen on.
+pub fn custom_handler_address(config: &Config) -> SocketAddr {
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), config.custom_handler_port)
+}
+
+pub async fn serve(app: Router, address: SocketAddr) {
+    println!("Ready! Listening on {}", address);
+    axum::Server::bind(&address)
+        .serve(app.into_make_service())
+        .await
+        .unwrap();
+}
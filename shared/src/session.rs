@@ -0,0 +1,142 @@
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::distributions::{Alphanumeric, DistString};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{AuthError, Config};
+
+/// Maximum number of sessions kept for a single user, the oldest ones are dropped first.
+pub const MAX_SESSIONS: usize = 10;
+
+/// A login from a single device, holding hashes of the tokens the device authenticates with.
+/// A user can have several sessions at once, e.g. a phone and a tablet.
+/// Raw tokens are only ever handed to the client, so a leaked database can't be used to impersonate users.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Session {
+    pub id: String,
+    /// Device identifier sent by the client at login, so that logging in again from the same device
+    /// replaces its previous session instead of piling up new ones.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    pub access_token_hash: String,
+    pub refresh_token_hash: String,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub refresh_expires_at: DateTime<Utc>,
+    /// Set when the session is revoked (logout), after which neither token is accepted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Raw tokens of a session, returned to the client when the session is created or refreshed.
+#[derive(Serialize, Clone, Debug)]
+pub struct SessionTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_at: DateTime<Utc>,
+    pub refresh_expires_at: DateTime<Utc>,
+}
+
+/// Keyed hash (HMAC-SHA256, hex encoded) under which a token is stored and looked up.
+pub fn hash_token(secret: &str, token: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(token.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+pub fn generate_token(length: usize) -> String {
+    let mut rng = rand::thread_rng();
+
+    // https://fly.io/blog/api-tokens-a-tedious-survey/ random tokens are a reasonable choice for a simple auth system like this that doesn't require policies
+    Alphanumeric.sample_string(&mut rng, length)
+}
+
+impl Session {
+    /// Starts a new session with freshly generated tokens, which are only returned here.
+    pub fn new(device: Option<String>, config: &Config) -> (Self, SessionTokens) {
+        let mut session = Session {
+            id: generate_token(16),
+            device,
+            access_token_hash: String::new(),
+            refresh_token_hash: String::new(),
+            issued_at: Utc::now(),
+            expires_at: Utc::now(),
+            refresh_expires_at: Utc::now(),
+            revoked_at: None,
+        };
+        let tokens = session.rotate(config);
+        (session, tokens)
+    }
+
+    /// Replaces both tokens, invalidating the previous ones (refresh token rotation).
+    pub fn rotate(&mut self, config: &Config) -> SessionTokens {
+        let now = Utc::now();
+        let tokens = SessionTokens {
+            access_token: generate_token(32),
+            refresh_token: generate_token(48),
+            expires_at: now + config.access_token_ttl,
+            refresh_expires_at: now + config.refresh_token_ttl,
+        };
+        self.access_token_hash = hash_token(&config.token_hash_secret, &tokens.access_token);
+        self.refresh_token_hash = hash_token(&config.token_hash_secret, &tokens.refresh_token);
+        self.issued_at = now;
+        self.expires_at = tokens.expires_at;
+        self.refresh_expires_at = tokens.refresh_expires_at;
+        tokens
+    }
+
+    pub fn revoke(&mut self) {
+        self.revoked_at.get_or_insert_with(Utc::now);
+    }
+
+    /// Checks that the access token of this session can still be used.
+    pub fn check_access(&self) -> Result<(), AuthError> {
+        if self.revoked_at.is_some() {
+            return Err(AuthError::RevokedToken);
+        }
+        if self.expires_at <= Utc::now() {
+            return Err(AuthError::ExpiredToken);
+        }
+        Ok(())
+    }
+
+    /// Checks that the refresh token of this session can still be used.
+    pub fn check_refresh(&self) -> Result<(), AuthError> {
+        if self.revoked_at.is_some() {
+            return Err(AuthError::RevokedToken);
+        }
+        if self.refresh_expires_at <= Utc::now() {
+            return Err(AuthError::ExpiredToken);
+        }
+        Ok(())
+    }
+
+    /// Sessions that can't be refreshed anymore are useless and can be dropped.
+    fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        self.refresh_expires_at <= now
+            || self
+                .revoked_at
+                .is_some_and(|revoked_at| now - revoked_at > Duration::days(1))
+    }
+}
+
+/// Adds a session to the list, replacing any previous session of the same device and dropping
+/// stale ones, so that the list can't grow unbounded.
+pub fn add_session(sessions: &mut Vec<Session>, session: Session) {
+    let now = Utc::now();
+    sessions.retain(|existing| {
+        !existing.is_stale(now) && (session.device.is_none() || existing.device != session.device)
+    });
+    sessions.push(session);
+    if sessions.len() > MAX_SESSIONS {
+        sessions.sort_by_key(|session| session.issued_at);
+        let excess = sessions.len() - MAX_SESSIONS;
+        sessions.drain(..excess);
+    }
+}
@@ -0,0 +1,255 @@
+use async_trait::async_trait;
+use azure_core::StatusCode;
+use azure_data_cosmos::prelude::{
+    CollectionClient, GetDocumentResponse, IfMatchCondition, Param, Query,
+};
+use futures::StreamExt;
+
+use crate::{
+    migrations::{extract_matches, upgrade_document, CURRENT_SCHEMA_VERSION},
+    AppError, AuthError, Config, Identity, IndexAction, IndexActionType, MatchStore, UserDocument,
+    UserSearchData, VectorIndex,
+};
+
+use super::{UserStore, Versioned};
+
+/// User store backed by the Cosmos DB users collection.
+pub struct CosmosUserStore {
+    collection_client: CollectionClient,
+}
+
+impl CosmosUserStore {
+    pub fn new(collection_client: CollectionClient) -> Self {
+        CosmosUserStore { collection_client }
+    }
+
+    /// Runs a query expected to match at most one user, returning the first result.
+    async fn query_single(&self, query: Query) -> Result<Option<UserDocument>, AppError> {
+        let mut docs_stream = self
+            .collection_client
+            .query_documents(query)
+            .query_cross_partition(true) //TODO deep dive and figure out how to do in partition queries for this case
+            .max_item_count(1)
+            .into_stream::<serde_json::Value>();
+
+        if let Some(query_response) = docs_stream.next().await {
+            println!("User document found, {:?}", query_response);
+            // In this page, the documents are under results
+            let query_response = query_response?;
+            if let Some((document, _)) = query_response.results.into_iter().next() {
+                return current_document(document).map(Some);
+            }
+            println!("Empty doc array");
+        }
+        Ok(None)
+    }
+
+    /// Upgrades every document written with an older layout, returning how many were upgraded.
+    /// Embedded matches are moved to the match store along the way, and the search data of upgraded
+    /// documents is pushed to the vector index again.
+    pub async fn migrate_documents(
+        &self,
+        config: &Config,
+        match_store: &dyn MatchStore,
+        vector_index: &dyn VectorIndex,
+    ) -> Result<usize, AppError> {
+        let mut docs_stream = self
+            .collection_client
+            .query_documents(Query::with_params(
+                "SELECT * FROM users AS u
+                 WHERE NOT IS_DEFINED(u.schema_version) OR u.schema_version < @version"
+                    .to_owned(),
+                vec![Param::new("@version".into(), CURRENT_SCHEMA_VERSION)],
+            ))
+            .query_cross_partition(true)
+            .into_stream::<serde_json::Value>();
+
+        let mut migrated = 0;
+        while let Some(query_response) = docs_stream.next().await {
+            for (mut document, _) in query_response?.results {
+                let extracted = extract_matches(&document, match_store).await?;
+                if extracted > 0 {
+                    println!("Extracted {} matches of user {}", extracted, document["id"]);
+                }
+                if !upgrade_document(&mut document, config)? {
+                    continue;
+                }
+                let user_document: UserDocument = serde_json::from_value(document)?;
+                self.replace(&user_document).await?;
+                println!("User {} migrated", user_document.id);
+                vector_index
+                    .index_documents(&[IndexAction {
+                        action_type: IndexActionType::Merge,
+                        user_document: UserSearchData::from(user_document),
+                    }])
+                    .await?;
+                migrated += 1;
+            }
+        }
+        Ok(migrated)
+    }
+}
+
+/// Reads a raw user document, refusing the ones written with an older layout: they may not even
+/// deserialize (e.g. locations stored latitude first) and would lose their legacy fields if written
+/// back, so they have to be upgraded by the `migrate` command first.
+fn current_document(document: serde_json::Value) -> Result<UserDocument, AppError> {
+    let version = document
+        .get("schema_version")
+        .and_then(serde_json::Value::as_u64)
+        .unwrap_or(0);
+    if version < CURRENT_SCHEMA_VERSION as u64 {
+        println!(
+            "User {} has the outdated layout version {}, run the migrate command",
+            document["id"], version
+        );
+        return Err(AppError::GenericError);
+    }
+    Ok(serde_json::from_value(document)?)
+}
+
+/// Only the migration upgrades documents, so outdated ones are never written back as if they were current.
+fn check_layout(user_document: &UserDocument) -> Result<(), AppError> {
+    if user_document.schema_version < CURRENT_SCHEMA_VERSION {
+        println!(
+            "Refusing to write user {} with the outdated layout version {}",
+            user_document.id, user_document.schema_version
+        );
+        return Err(AppError::GenericError);
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl UserStore for CosmosUserStore {
+    async fn get_by_token_hash(&self, token_hash: &str) -> Result<UserDocument, AuthError> {
+        println!("Querying user doc with token hash: {:?}", token_hash);
+        self.query_single(Query::with_params(
+            "SELECT VALUE u FROM users AS u JOIN s IN u.sessions WHERE s.access_token_hash = @token"
+                .to_owned(),
+            vec![Param::new("@token".into(), token_hash)],
+        ))
+        .await
+        .ok()
+        .flatten()
+        .ok_or(AuthError::InvalidToken)
+    }
+
+    async fn get_by_refresh_token_hash(&self, token_hash: &str) -> Result<UserDocument, AuthError> {
+        println!("Querying user doc with refresh token hash: {:?}", token_hash);
+        self.query_single(Query::with_params(
+            "SELECT VALUE u FROM users AS u JOIN s IN u.sessions WHERE s.refresh_token_hash = @token"
+                .to_owned(),
+            vec![Param::new("@token".into(), token_hash)],
+        ))
+        .await
+        .ok()
+        .flatten()
+        .ok_or(AuthError::InvalidToken)
+    }
+
+    async fn get_by_id(&self, id: &str) -> Result<UserDocument, AppError> {
+        println!("Querying user doc with id: {:?}", id);
+        let response = self
+            .collection_client
+            .document_client(id, &id)?
+            .get_document::<serde_json::Value>()
+            .await
+            .map_err(|_| AuthError::InvalidToken)?;
+
+        match response {
+            GetDocumentResponse::Found(document) => current_document(document.document.document),
+            _ => Err(AppError::NotFoundError),
+        }
+    }
+
+    async fn get_by_email(&self, email: &str) -> Result<UserDocument, AppError> {
+        println!("Querying user doc with email: {:?}", email);
+        self.query_single(Query::with_params(
+            "SELECT * FROM users AS u WHERE u.email = @email".to_owned(),
+            vec![Param::new("@email".into(), email)],
+        ))
+        .await?
+        .ok_or(AppError::NotFoundError)
+    }
+
+    async fn get_by_identity(&self, identity: &Identity) -> Result<UserDocument, AppError> {
+        println!("Querying user doc with identity: {:?}", identity);
+        self.query_single(Query::with_params(
+            "SELECT VALUE u FROM users AS u JOIN i IN u.identities
+             WHERE i.provider = @provider AND i.subject = @subject"
+                .to_owned(),
+            vec![
+                Param::new("@provider".into(), identity.provider.clone()),
+                Param::new("@subject".into(), identity.subject.clone()),
+            ],
+        ))
+        .await?
+        .ok_or(AppError::NotFoundError)
+    }
+
+    async fn upsert(&self, user_document: &UserDocument) -> Result<(), AppError> {
+        check_layout(user_document)?;
+        self.collection_client
+            .create_document(user_document.clone())
+            .is_upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    async fn replace(&self, user_document: &UserDocument) -> Result<(), AppError> {
+        check_layout(user_document)?;
+        self.collection_client
+            .document_client(user_document.id.clone(), &user_document.id)?
+            .replace_document(user_document.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn get_versioned(&self, id: &str) -> Result<Versioned<UserDocument>, AppError> {
+        let response = self
+            .collection_client
+            .document_client(id, &id)?
+            .get_document::<serde_json::Value>()
+            .await?;
+
+        match response {
+            GetDocumentResponse::Found(document) => Ok(Versioned {
+                etag: document.document.document_attributes.etag().to_owned(),
+                document: current_document(document.document.document)?,
+            }),
+            _ => Err(AppError::NotFoundError),
+        }
+    }
+
+    async fn replace_if_match(&self, user_document: &UserDocument, etag: &str) -> Result<(), AppError> {
+        check_layout(user_document)?;
+        let result = self
+            .collection_client
+            .document_client(user_document.id.clone(), &user_document.id)?
+            .replace_document(user_document.clone())
+            .if_match_condition(IfMatchCondition::Match(etag.to_owned()))
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(err)
+                if err
+                    .as_http_error()
+                    .is_some_and(|err| err.status() == StatusCode::PreconditionFailed) =>
+            {
+                println!("User {} was modified concurrently", user_document.id);
+                Err(AppError::Conflict)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), AppError> {
+        self.collection_client
+            .document_client(id, &id)?
+            .delete_document()
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use async_trait::async_trait;
+
+use crate::{AppError, AuthError, Identity, UserDocument};
+
+use super::{UserStore, Versioned};
+
+/// A stored document and the number of times it was written, used as its etag.
+struct StoredDocument {
+    document: UserDocument,
+    version: u64,
+}
+
+/// Thread-safe user store keeping every document in memory, keyed by id.
+/// Meant for local development and tests, nothing is persisted.
+#[derive(Default)]
+pub struct InMemoryUserStore {
+    documents: RwLock<HashMap<String, StoredDocument>>,
+}
+
+impl InMemoryUserStore {
+    fn find(&self, predicate: impl Fn(&UserDocument) -> bool) -> Option<UserDocument> {
+        self.documents
+            .read()
+            .unwrap()
+            .values()
+            .map(|stored| &stored.document)
+            .find(|document| predicate(document))
+            .cloned()
+    }
+
+    fn write(&self, user_document: &UserDocument, etag: Option<&str>) -> Result<(), AppError> {
+        let mut documents = self.documents.write().unwrap();
+        let existing = documents
+            .get_mut(&user_document.id)
+            .ok_or(AppError::NotFoundError)?;
+        if etag.is_some_and(|etag| etag != existing.version.to_string()) {
+            return Err(AppError::Conflict);
+        }
+        existing.document = user_document.clone();
+        existing.version += 1;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserStore for InMemoryUserStore {
+    async fn get_by_token_hash(&self, token_hash: &str) -> Result<UserDocument, AuthError> {
+        self.find(|document| document.session_by_access_token_hash(token_hash).is_some())
+            .ok_or(AuthError::InvalidToken)
+    }
+
+    async fn get_by_refresh_token_hash(&self, token_hash: &str) -> Result<UserDocument, AuthError> {
+        self.find(|document| {
+            document
+                .sessions
+                .iter()
+                .any(|session| session.refresh_token_hash == token_hash)
+        })
+        .ok_or(AuthError::InvalidToken)
+    }
+
+    async fn get_by_id(&self, id: &str) -> Result<UserDocument, AppError> {
+        self.documents
+            .read()
+            .unwrap()
+            .get(id)
+            .map(|stored| stored.document.clone())
+            .ok_or(AppError::NotFoundError)
+    }
+
+    async fn get_by_email(&self, email: &str) -> Result<UserDocument, AppError> {
+        self.find(|document| document.email == email)
+            .ok_or(AppError::NotFoundError)
+    }
+
+    async fn get_by_identity(&self, identity: &Identity) -> Result<UserDocument, AppError> {
+        self.find(|document| document.identities.contains(identity))
+            .ok_or(AppError::NotFoundError)
+    }
+
+    async fn upsert(&self, user_document: &UserDocument) -> Result<(), AppError> {
+        let mut documents = self.documents.write().unwrap();
+        let stored = documents
+            .entry(user_document.id.clone())
+            .or_insert_with(|| StoredDocument {
+                document: user_document.clone(),
+                version: 0,
+            });
+        stored.document = user_document.clone();
+        stored.version += 1;
+        Ok(())
+    }
+
+    async fn replace(&self, user_document: &UserDocument) -> Result<(), AppError> {
+        self.write(user_document, None)
+    }
+
+    async fn get_versioned(&self, id: &str) -> Result<Versioned<UserDocument>, AppError> {
+        self.documents
+            .read()
+            .unwrap()
+            .get(id)
+            .map(|stored| Versioned {
+                document: stored.document.clone(),
+                etag: stored.version.to_string(),
+            })
+            .ok_or(AppError::NotFoundError)
+    }
+
+    async fn replace_if_match(&self, user_document: &UserDocument, etag: &str) -> Result<(), AppError> {
+        self.write(user_document, Some(etag))
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), AppError> {
+        self.documents
+            .write()
+            .unwrap()
+            .remove(id)
+            .map(|_| ())
+            .ok_or(AppError::NotFoundError)
+    }
+}
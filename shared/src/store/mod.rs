@@ -0,0 +1,119 @@
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+
+use crate::{config::UserStoreConfig, AppError, AuthError, Identity, UserDocument};
+
+mod cosmos;
+mod memory;
+pub(crate) mod sqlite;
+
+pub use cosmos::CosmosUserStore;
+pub use memory::InMemoryUserStore;
+pub use sqlite::SqliteUserStore;
+
+/// Attempts made before giving up on a change of a user that keeps conflicting with concurrent ones.
+pub const MAX_UPDATE_ATTEMPTS: usize = 5;
+
+/// Persistence layer for user documents.
+/// Every function talks to the users collection only through this trait, so the backend can be swapped
+/// (e.g. an in-memory store for running the functions offline).
+#[async_trait]
+pub trait UserStore: Send + Sync {
+    /// Fetches the user owning a session with the given access token hash, whether it's still valid or not.
+    async fn get_by_token_hash(&self, token_hash: &str) -> Result<UserDocument, AuthError>;
+
+    /// Fetches the user owning a session with the given refresh token hash, whether it's still valid or not.
+    async fn get_by_refresh_token_hash(&self, token_hash: &str) -> Result<UserDocument, AuthError>;
+
+    async fn get_by_id(&self, id: &str) -> Result<UserDocument, AppError>;
+
+    async fn get_by_email(&self, email: &str) -> Result<UserDocument, AppError>;
+
+    /// Fetches the user the given identity provider account is linked to.
+    async fn get_by_identity(&self, identity: &Identity) -> Result<UserDocument, AppError>;
+
+    /// Creates the document, or overwrites it if a document with the same id already exists.
+    async fn upsert(&self, user_document: &UserDocument) -> Result<(), AppError>;
+
+    /// Overwrites an existing document, failing with `AppError::NotFoundError` if it does not exist.
+    async fn replace(&self, user_document: &UserDocument) -> Result<(), AppError>;
+
+    /// Fetches a document along with its current version, for optimistic concurrency.
+    async fn get_versioned(&self, id: &str) -> Result<Versioned<UserDocument>, AppError>;
+
+    /// Overwrites an existing document only if it's still at the version it was read at, failing
+    /// with `AppError::Conflict` if it was modified in the meantime.
+    async fn replace_if_match(&self, user_document: &UserDocument, etag: &str) -> Result<(), AppError>;
+
+    async fn delete(&self, id: &str) -> Result<(), AppError>;
+}
+
+/// A document along with an opaque version tag, which changes on every write.
+#[derive(Clone, Debug)]
+pub struct Versioned<T> {
+    pub document: T,
+    pub etag: String,
+}
+
+/// Runs an optimistic update again as long as it fails with `AppError::Conflict`, up to
+/// [`MAX_UPDATE_ATTEMPTS`] times. Each attempt should read the latest version of what it changes and
+/// write it only if it's still at that version. `description` names what's updated in the logs.
+pub async fn retry_on_conflict<T, F, Fut>(description: &str, mut attempt: F) -> Result<T, AppError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, AppError>>,
+{
+    for attempt_number in 1..=MAX_UPDATE_ATTEMPTS {
+        match attempt().await {
+            Err(AppError::Conflict) => {
+                println!("{} changed concurrently, attempt {}", description, attempt_number);
+            }
+            result => return result,
+        }
+    }
+    Err(AppError::Conflict)
+}
+
+/// Applies a change to the latest version of a user and writes it, unless `change` returns `false`
+/// because there's nothing to write. The document is written only if it didn't change since it was
+/// read, so that concurrent changes (e.g. a login while the location is synced) aren't lost: `change`
+/// runs again on the latest version instead. Returns the document as written.
+pub async fn update_user<F>(user_store: &dyn UserStore, id: &str, change: F) -> Result<UserDocument, AppError>
+where
+    F: FnMut(&mut UserDocument) -> Result<bool, AppError> + Send,
+{
+    // Every attempt borrows the change, which the attempts can't do mutably
+    let change = Mutex::new(change);
+    let change = &change;
+    retry_on_conflict(&format!("User {}", id), || async move {
+        let Versioned { mut document, etag } = user_store.get_versioned(id).await?;
+        let changed = (change.lock().unwrap())(&mut document)?;
+        if !changed {
+            return Ok(document);
+        }
+        user_store.replace_if_match(&document, &etag).await.map(|_| document)
+    })
+    .await
+}
+
+/// Builds the configured user store.
+pub async fn get_user_store(user_store_config: &UserStoreConfig) -> Arc<dyn UserStore> {
+    match user_store_config {
+        UserStoreConfig::Cosmos(cosmos_config) => Arc::new(CosmosUserStore::new(
+            crate::get_collection_client(cosmos_config, &cosmos_config.users_collection)
+                .await
+                .unwrap(),
+        )),
+        UserStoreConfig::Sqlite { path } => {
+            Arc::new(SqliteUserStore::open(path).expect("Could not open the SQLite database"))
+        }
+        UserStoreConfig::Memory => {
+            println!("Using the in-memory user store, data will be lost on shutdown");
+            Arc::new(InMemoryUserStore::default())
+        }
+    }
+}
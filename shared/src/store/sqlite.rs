@@ -0,0 +1,566 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OptionalExtension, Params, Row, Transaction};
+
+use crate::{
+    migrations::CURRENT_SCHEMA_VERSION, AppError, AuthError, Identity, GeoPoint, MatchStatus, Session,
+    UserDocument,
+};
+
+use super::{UserStore, Versioned};
+
+/// Schema migrations, applied in order. The index of the last applied migration is tracked through
+/// SQLite's `user_version` pragma, so new migrations must only ever be appended to this list.
+const MIGRATIONS: &[&str] = &[
+    // 1: users, their locations and their matches
+    "CREATE TABLE users (
+        id TEXT PRIMARY KEY,
+        email TEXT NOT NULL,
+        name TEXT NOT NULL,
+        access_token TEXT NOT NULL,
+        description TEXT,
+        description_embeddings TEXT
+    );
+    CREATE INDEX users_access_token ON users (access_token);
+    CREATE INDEX users_email ON users (email);
+    CREATE TABLE locations (
+        user_id TEXT PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+        latitude REAL NOT NULL,
+        longitude REAL NOT NULL
+    );
+    CREATE TABLE matches (
+        user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+        target_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        description TEXT NOT NULL,
+        match_status INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (user_id, target_id)
+    );",
+    // 2: multiple sessions per user, replacing the single access token
+    "DROP INDEX users_access_token;
+    ALTER TABLE users DROP COLUMN access_token;
+    CREATE TABLE sessions (
+        user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+        id TEXT NOT NULL,
+        device TEXT,
+        access_token TEXT NOT NULL UNIQUE,
+        refresh_token TEXT NOT NULL UNIQUE,
+        issued_at TEXT NOT NULL,
+        expires_at TEXT NOT NULL,
+        refresh_expires_at TEXT NOT NULL,
+        revoked_at TEXT,
+        PRIMARY KEY (user_id, id)
+    );",
+    // 3: only token hashes are stored. Raw tokens can't be hashed from SQL, so existing sessions are
+    // dropped and their users have to log in again.
+    "DELETE FROM sessions;
+    ALTER TABLE sessions RENAME COLUMN access_token TO access_token_hash;
+    ALTER TABLE sessions RENAME COLUMN refresh_token TO refresh_token_hash;",
+    // 4: identity provider accounts linked to users
+    "CREATE TABLE identities (
+        user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+        provider TEXT NOT NULL,
+        subject TEXT NOT NULL,
+        PRIMARY KEY (provider, subject)
+    );",
+    // 5: version of each user, incremented on every write, for optimistic concurrency
+    "ALTER TABLE users ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE matches ADD COLUMN revision INTEGER NOT NULL DEFAULT 0;",
+    // 6: a single record per pair of users, replacing the copy of the match each user had. Of the
+    // two copies the one with the highest revision wins, or the one of the smallest user id on a tie.
+    // The status is stored as seen by the user with the smallest id.
+    "CREATE TABLE match_records (
+        id TEXT PRIMARY KEY,
+        first_user_id TEXT NOT NULL,
+        second_user_id TEXT NOT NULL,
+        status INTEGER NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        version INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX match_records_first_user ON match_records (first_user_id, updated_at);
+    CREATE INDEX match_records_second_user ON match_records (second_user_id, updated_at);
+    INSERT INTO match_records (id, first_user_id, second_user_id, status, created_at, updated_at)
+    SELECT
+        MIN(m.user_id, m.target_id) || ':' || MAX(m.user_id, m.target_id),
+        MIN(m.user_id, m.target_id),
+        MAX(m.user_id, m.target_id),
+        CASE
+            WHEN m.user_id < m.target_id THEN m.match_status
+            WHEN m.match_status = 1 THEN 2
+            WHEN m.match_status = 2 THEN 1
+            ELSE m.match_status
+        END,
+        strftime('%Y-%m-%d %H:%M:%S', 'now'),
+        strftime('%Y-%m-%d %H:%M:%S', 'now')
+    FROM matches AS m
+    WHERE NOT EXISTS (
+        SELECT 1 FROM matches AS o
+        WHERE o.user_id = m.target_id AND o.target_id = m.user_id
+            AND (o.revision > m.revision OR (o.revision = m.revision AND o.user_id < m.user_id))
+    );
+    DROP TABLE matches;",
+    // 7: users blocked by each user, and reports of abusive users
+    "CREATE TABLE blocks (
+        user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+        blocked_user_id TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (user_id, blocked_user_id)
+    );
+    CREATE INDEX blocks_blocked_user ON blocks (blocked_user_id);
+    CREATE TABLE reports (
+        id TEXT PRIMARY KEY,
+        reporter_id TEXT NOT NULL,
+        reported_user_id TEXT NOT NULL,
+        reason TEXT NOT NULL,
+        details TEXT,
+        created_at TEXT NOT NULL
+    );
+    CREATE INDEX reports_reported_user ON reports (reported_user_id);",
+    // 8: catalogue of the points of interest suggested as meeting points
+    "CREATE TABLE pois (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        address TEXT,
+        category TEXT NOT NULL,
+        opening_hours TEXT,
+        partner INTEGER NOT NULL,
+        latitude REAL NOT NULL,
+        longitude REAL NOT NULL
+    );
+    CREATE INDEX pois_latitude ON pois (latitude);",
+    // 9: meeting points chosen for groups of users
+    "CREATE TABLE meetups (
+        id TEXT PRIMARY KEY,
+        organizer_id TEXT NOT NULL,
+        poi TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+    CREATE TABLE meetup_participants (
+        meetup_id TEXT NOT NULL REFERENCES meetups (id) ON DELETE CASCADE,
+        user_id TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (meetup_id, user_id)
+    );
+    CREATE INDEX meetup_participants_user ON meetup_participants (user_id);",
+    // 10: meetings negotiated between two users, with their whole history in the document
+    "CREATE TABLE meetings (
+        id TEXT PRIMARY KEY,
+        first_user_id TEXT NOT NULL,
+        second_user_id TEXT NOT NULL,
+        status TEXT NOT NULL,
+        document TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        version INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX meetings_first_user ON meetings (first_user_id);
+    CREATE INDEX meetings_second_user ON meetings (second_user_id);",
+    // 11: calendar feeds of the users, and time zones of the points of interest for calendar events
+    "ALTER TABLE users ADD COLUMN calendar_feed_token_hash TEXT;
+    ALTER TABLE pois ADD COLUMN timezone TEXT;",
+];
+
+/// User store backed by an embedded SQLite database file, for deployments without Cosmos DB.
+pub struct SqliteUserStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteUserStore {
+    /// Opens (or creates) the database at the given path and brings its schema up to date.
+    pub fn open(path: &str) -> Result<Self, AppError> {
+        Ok(SqliteUserStore {
+            connection: Arc::new(Mutex::new(open_connection(path)?)),
+        })
+    }
+
+    async fn run<T, F>(&self, operation: F) -> Result<T, AppError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T, AppError> + Send + 'static,
+    {
+        run_blocking(&self.connection, operation).await
+    }
+}
+
+/// Opens (or creates) the database at the given path and brings its schema up to date.
+/// The user and match stores each hold a connection to the same database.
+pub(crate) fn open_connection(path: &str) -> Result<Connection, AppError> {
+    let mut connection = Connection::open(path)?;
+    connection.pragma_update(None, "foreign_keys", "ON")?;
+    connection.busy_timeout(Duration::from_secs(5))?;
+    migrate(&mut connection)?;
+    Ok(connection)
+}
+
+/// Runs a blocking database operation outside of the async runtime's worker threads.
+pub(crate) async fn run_blocking<T, F>(
+    connection: &Arc<Mutex<Connection>>,
+    operation: F,
+) -> Result<T, AppError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut Connection) -> Result<T, AppError> + Send + 'static,
+{
+    let connection = connection.clone();
+    tokio::task::spawn_blocking(move || operation(&mut connection.lock().unwrap()))
+        .await
+        .map_err(|_| AppError::GenericError)?
+}
+
+fn migrate(connection: &mut Connection) -> Result<(), AppError> {
+    let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        println!("Applying SQLite migration {}", index + 1);
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", index + 1)?;
+        transaction.commit()?;
+    }
+    Ok(())
+}
+
+/// Reads the match status stored in the column at `index` of the row.
+pub(crate) fn match_status_from_sql(row: &Row, index: usize) -> rusqlite::Result<MatchStatus> {
+    let value: i64 = row.get(index)?;
+    match value {
+        1 => Ok(MatchStatus::Pending),
+        2 => Ok(MatchStatus::AwaitingUserAction),
+        3 => Ok(MatchStatus::Accepted),
+        4 => Ok(MatchStatus::Denied),
+        5 => Ok(MatchStatus::Cancelled),
+        _ => Err(rusqlite::Error::IntegralValueOutOfRange(index, value)),
+    }
+}
+
+/// Loads the first user matching `condition` (an SQL expression using the given parameters), along
+/// with its identities, sessions, location and blocked users.
+fn load_user(
+    connection: &Connection,
+    condition: &str,
+    params: impl Params,
+) -> Result<Option<UserDocument>, AppError> {
+    let user_row = connection
+        .query_row(
+            &format!(
+                "SELECT id, email, name, description, description_embeddings, calendar_feed_token_hash
+                 FROM users WHERE {} LIMIT 1",
+                condition
+            ),
+            params,
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                ))
+            },
+        )
+        .optional()?;
+    let Some((id, email, name, description, description_embeddings, calendar_feed_token_hash)) = user_row
+    else {
+        return Ok(None);
+    };
+
+    let description_embeddings = description_embeddings
+        .map(|embeddings| serde_json::from_str::<Vec<f64>>(&embeddings))
+        .transpose()?;
+
+    let identities = connection
+        .prepare("SELECT provider, subject FROM identities WHERE user_id = ?1")?
+        .query_map([&id], |row| {
+            Ok(Identity {
+                provider: row.get(0)?,
+                subject: row.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let sessions = connection
+        .prepare(
+            "SELECT id, device, access_token_hash, refresh_token_hash, issued_at, expires_at,
+                refresh_expires_at, revoked_at
+             FROM sessions WHERE user_id = ?1 ORDER BY issued_at",
+        )?
+        .query_map([&id], |row| {
+            Ok(Session {
+                id: row.get(0)?,
+                device: row.get(1)?,
+                access_token_hash: row.get(2)?,
+                refresh_token_hash: row.get(3)?,
+                issued_at: row.get(4)?,
+                expires_at: row.get(5)?,
+                refresh_expires_at: row.get(6)?,
+                revoked_at: row.get(7)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let location = connection
+        .query_row(
+            "SELECT latitude, longitude FROM locations WHERE user_id = ?1",
+            [&id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .map(|(latitude, longitude)| GeoPoint::new(latitude, longitude))
+        .transpose()?;
+
+    let blocked_user_ids = connection
+        .prepare("SELECT blocked_user_id FROM blocks WHERE user_id = ?1 ORDER BY position")?
+        .query_map([&id], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+
+    Ok(Some(UserDocument {
+        id,
+        // The layout of the documents is handled by the SQL migrations
+        schema_version: CURRENT_SCHEMA_VERSION,
+        email,
+        name,
+        identities,
+        sessions,
+        description,
+        description_embeddings,
+        location,
+        blocked_user_ids,
+        calendar_feed_token_hash,
+    }))
+}
+
+fn load_version(connection: &Connection, id: &str) -> Result<Option<i64>, AppError> {
+    Ok(connection
+        .query_row("SELECT version FROM users WHERE id = ?1", [id], |row| row.get(0))
+        .optional()?)
+}
+
+/// Writes the whole document, replacing any previously stored identities, sessions, location and blocked users.
+fn save_user(transaction: &Transaction, user_document: &UserDocument) -> Result<(), AppError> {
+    let description_embeddings = user_document
+        .description_embeddings
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()?;
+
+    transaction.execute(
+        "INSERT INTO users (id, email, name, description, description_embeddings, calendar_feed_token_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT (id) DO UPDATE SET
+            email = excluded.email,
+            name = excluded.name,
+            description = excluded.description,
+            description_embeddings = excluded.description_embeddings,
+            calendar_feed_token_hash = excluded.calendar_feed_token_hash,
+            version = users.version + 1",
+        params![
+            user_document.id,
+            user_document.email,
+            user_document.name,
+            user_document.description,
+            description_embeddings,
+            user_document.calendar_feed_token_hash,
+        ],
+    )?;
+
+    transaction.execute("DELETE FROM identities WHERE user_id = ?1", [&user_document.id])?;
+    for identity in &user_document.identities {
+        transaction.execute(
+            "INSERT INTO identities (user_id, provider, subject) VALUES (?1, ?2, ?3)",
+            params![user_document.id, identity.provider, identity.subject],
+        )?;
+    }
+
+    transaction.execute("DELETE FROM sessions WHERE user_id = ?1", [&user_document.id])?;
+    for session in &user_document.sessions {
+        transaction.execute(
+            "INSERT INTO sessions (user_id, id, device, access_token_hash, refresh_token_hash, issued_at,
+                expires_at, refresh_expires_at, revoked_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                user_document.id,
+                session.id,
+                session.device,
+                session.access_token_hash,
+                session.refresh_token_hash,
+                session.issued_at,
+                session.expires_at,
+                session.refresh_expires_at,
+                session.revoked_at,
+            ],
+        )?;
+    }
+
+    transaction.execute(
+        "DELETE FROM locations WHERE user_id = ?1",
+        [&user_document.id],
+    )?;
+    if let Some(location) = &user_document.location {
+        transaction.execute(
+            "INSERT INTO locations (user_id, latitude, longitude) VALUES (?1, ?2, ?3)",
+            params![
+                user_document.id,
+                location.latitude(),
+                location.longitude()
+            ],
+        )?;
+    }
+
+    transaction.execute("DELETE FROM blocks WHERE user_id = ?1", [&user_document.id])?;
+    for (position, blocked_user_id) in user_document.blocked_user_ids.iter().enumerate() {
+        transaction.execute(
+            "INSERT INTO blocks (user_id, blocked_user_id, position) VALUES (?1, ?2, ?3)",
+            params![user_document.id, blocked_user_id, position],
+        )?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl UserStore for SqliteUserStore {
+    async fn get_by_token_hash(&self, token_hash: &str) -> Result<UserDocument, AuthError> {
+        let token_hash = token_hash.to_owned();
+        self.run(move |connection| {
+            load_user(
+                connection,
+                "id = (SELECT user_id FROM sessions WHERE access_token_hash = ?1)",
+                [&token_hash],
+            )
+        })
+        .await
+        .ok()
+        .flatten()
+        .ok_or(AuthError::InvalidToken)
+    }
+
+    async fn get_by_refresh_token_hash(&self, token_hash: &str) -> Result<UserDocument, AuthError> {
+        let token_hash = token_hash.to_owned();
+        self.run(move |connection| {
+            load_user(
+                connection,
+                "id = (SELECT user_id FROM sessions WHERE refresh_token_hash = ?1)",
+                [&token_hash],
+            )
+        })
+        .await
+        .ok()
+        .flatten()
+        .ok_or(AuthError::InvalidToken)
+    }
+
+    async fn get_by_id(&self, id: &str) -> Result<UserDocument, AppError> {
+        let id = id.to_owned();
+        self.run(move |connection| load_user(connection, "id = ?1", [&id]))
+            .await?
+            .ok_or(AppError::NotFoundError)
+    }
+
+    async fn get_by_email(&self, email: &str) -> Result<UserDocument, AppError> {
+        let email = email.to_owned();
+        self.run(move |connection| load_user(connection, "email = ?1", [&email]))
+            .await?
+            .ok_or(AppError::NotFoundError)
+    }
+
+    async fn get_by_identity(&self, identity: &Identity) -> Result<UserDocument, AppError> {
+        let identity = identity.clone();
+        self.run(move |connection| {
+            load_user(
+                connection,
+                "id = (SELECT user_id FROM identities WHERE provider = ?1 AND subject = ?2)",
+                [&identity.provider, &identity.subject],
+            )
+        })
+        .await?
+        .ok_or(AppError::NotFoundError)
+    }
+
+    async fn upsert(&self, user_document: &UserDocument) -> Result<(), AppError> {
+        let user_document = user_document.clone();
+        self.run(move |connection| {
+            let transaction = connection.transaction()?;
+            save_user(&transaction, &user_document)?;
+            transaction.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn replace(&self, user_document: &UserDocument) -> Result<(), AppError> {
+        let user_document = user_document.clone();
+        self.run(move |connection| {
+            let transaction = connection.transaction()?;
+            load_version(&transaction, &user_document.id)?.ok_or(AppError::NotFoundError)?;
+            save_user(&transaction, &user_document)?;
+            transaction.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_versioned(&self, id: &str) -> Result<Versioned<UserDocument>, AppError> {
+        let id = id.to_owned();
+        self.run(move |connection| {
+            let transaction = connection.transaction()?;
+            let version = load_version(&transaction, &id)?.ok_or(AppError::NotFoundError)?;
+            let document = load_user(&transaction, "id = ?1", [&id])?.ok_or(AppError::NotFoundError)?;
+            Ok(Versioned {
+                document,
+                etag: version.to_string(),
+            })
+        })
+        .await
+    }
+
+    async fn replace_if_match(&self, user_document: &UserDocument, etag: &str) -> Result<(), AppError> {
+        let user_document = user_document.clone();
+        let etag = etag.to_owned();
+        self.run(move |connection| {
+            let transaction = connection.transaction()?;
+            let version = load_version(&transaction, &user_document.id)?.ok_or(AppError::NotFoundError)?;
+            if version.to_string() != etag {
+                println!("User {} was modified concurrently", user_document.id);
+                return Err(AppError::Conflict);
+            }
+            save_user(&transaction, &user_document)?;
+            transaction.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), AppError> {
+        let id = id.to_owned();
+        self.run(move |connection| {
+            match connection.execute("DELETE FROM users WHERE id = ?1", [&id])? {
+                0 => Err(AppError::NotFoundError),
+                _ => Ok(()),
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_match_status_reports_its_column() {
+        let connection = Connection::open_in_memory().unwrap();
+        let status = connection.query_row("SELECT 'id', 3", [], |row| match_status_from_sql(row, 1));
+        assert!(matches!(status, Ok(MatchStatus::Accepted)), "{:?}", status);
+        let status = connection.query_row("SELECT 'id', 9", [], |row| match_status_from_sql(row, 1));
+        assert!(
+            matches!(status, Err(rusqlite::Error::IntegralValueOutOfRange(1, 9))),
+            "{:?}",
+            status
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! What one user may see of the location of another. Every response showing the location of a user
+//! to someone else goes through [`location_precision`], so the rule lives in a single place.
+
+use crate::{GeoPoint, MatchRecord, MatchStatus, UserDocument};
+
+/// How precisely a user may see where another one is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LocationPrecision {
+    Hidden,
+    Exact,
+}
+
+impl LocationPrecision {
+    /// The location as it may be shown, `None` when hidden.
+    pub fn apply(self, location: &GeoPoint) -> Option<GeoPoint> {
+        match self {
+            LocationPrecision::Hidden => None,
+            LocationPrecision::Exact => Some(*location),
+        }
+    }
+}
+
+/// Precision of the location of `subject` that `viewer` may see, given the match between them if
+/// they have one. As documented on [`MatchStatus::Accepted`], only accepted matches see each other's
+/// location, so that they can meet. Users without a match, whose request is still waiting for an
+/// answer, or whose match was denied or cancelled see nothing. A block, whoever made it, always hides
+/// the location, and users always see their own.
+pub fn location_precision(
+    viewer: &UserDocument,
+    subject: &UserDocument,
+    record: Option<&MatchRecord>,
+) -> LocationPrecision {
+    let viewer_id = viewer.id.as_str();
+    if viewer_id == subject.id {
+        return LocationPrecision::Exact;
+    }
+    if viewer.is_blocked_with(subject) {
+        return LocationPrecision::Hidden;
+    }
+    // A match between other users says nothing about these two
+    let Some(record) = record.filter(|record| record.id == MatchRecord::pair_id(viewer_id, &subject.id)) else {
+        return LocationPrecision::Hidden;
+    };
+    match record.status_for(viewer_id) {
+        MatchStatus::Accepted => LocationPrecision::Exact,
+        MatchStatus::Pending
+        | MatchStatus::AwaitingUserAction
+        | MatchStatus::Denied
+        | MatchStatus::Cancelled => LocationPrecision::Hidden,
+    }
+}
+
+/// The location of `subject` as `viewer` may see it, if any.
+pub fn visible_location(
+    viewer: &UserDocument,
+    subject: &UserDocument,
+    record: Option<&MatchRecord>,
+) -> Option<GeoPoint> {
+    subject
+        .location
+        .and_then(|location| location_precision(viewer, subject, record).apply(&location))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUSES: [MatchStatus; 5] = [
+        MatchStatus::Pending,
+        MatchStatus::AwaitingUserAction,
+        MatchStatus::Accepted,
+        MatchStatus::Denied,
+        MatchStatus::Cancelled,
+    ];
+
+    fn user(id: &str) -> UserDocument {
+        UserDocument {
+            id: id.to_owned(),
+            schema_version: crate::migrations::CURRENT_SCHEMA_VERSION,
+            email: format!("{}@example.com", id),
+            name: id.to_owned(),
+            identities: Vec::new(),
+            sessions: Vec::new(),
+            description: None,
+            description_embeddings: None,
+            location: Some(GeoPoint::new(45.4642, 9.19).unwrap()),
+            blocked_user_ids: Vec::new(),
+            calendar_feed_token_hash: None,
+        }
+    }
+
+    fn expected(status: &MatchStatus) -> LocationPrecision {
+        match status {
+            MatchStatus::Accepted => LocationPrecision::Exact,
+            MatchStatus::Pending
+            | MatchStatus::AwaitingUserAction
+            | MatchStatus::Denied
+            | MatchStatus::Cancelled => LocationPrecision::Hidden,
+        }
+    }
+
+    #[test]
+    fn only_accepted_matches_see_the_location() {
+        let viewer = user("a");
+        let subject = user("b");
+        for status in STATUSES {
+            // Both ways around, since the record stores the status as seen by the smallest id
+            let record = MatchRecord::new("a", "b", status.clone());
+            assert_eq!(location_precision(&viewer, &subject, Some(&record)), expected(&status), "{:?}", status);
+            let record = MatchRecord::new("b", "a", status.mirrored());
+            assert_eq!(location_precision(&viewer, &subject, Some(&record)), expected(&status), "{:?}", status);
+
+            let location = visible_location(&viewer, &subject, Some(&record));
+            assert_eq!(location.is_some(), status == MatchStatus::Accepted, "{:?}", status);
+        }
+    }
+
+    #[test]
+    fn blocking_hides_accepted_matches() {
+        let viewer = user("a");
+        let mut subject = user("b");
+        subject.blocked_user_ids.push("a".to_owned());
+        let record = MatchRecord::new("a", "b", MatchStatus::Accepted);
+        assert_eq!(location_precision(&viewer, &subject, Some(&record)), LocationPrecision::Hidden);
+    }
+
+    #[test]
+    fn blocked_users_are_hidden_from_the_viewer_who_blocked_them() {
+        let mut viewer = user("a");
+        viewer.blocked_user_ids.push("b".to_owned());
+        let subject = user("b");
+        let record = MatchRecord::new("a", "b", MatchStatus::Accepted);
+        assert_eq!(location_precision(&viewer, &subject, Some(&record)), LocationPrecision::Hidden);
+        assert_eq!(visible_location(&viewer, &subject, Some(&record)), None);
+    }
+
+    #[test]
+    fn users_without_their_own_match_see_nothing() {
+        let viewer = user("a");
+        let subject = user("b");
+        assert_eq!(location_precision(&viewer, &subject, None), LocationPrecision::Hidden);
+        let record = MatchRecord::new("c", "b", MatchStatus::Accepted);
+        assert_eq!(location_precision(&viewer, &subject, Some(&record)), LocationPrecision::Hidden);
+    }
+
+    #[test]
+    fn users_see_their_own_location() {
+        let subject = user("a");
+        assert_eq!(location_precision(&subject, &subject, None), LocationPrecision::Exact);
+        assert_eq!(visible_location(&subject, &subject, None), subject.location);
+    }
+}